@@ -1,206 +1,13 @@
+use intcode::{parse_program, Computer, NoTrace};
+use std::collections::VecDeque;
 use std::io::{stdin, Read};
 
-#[derive(Debug)]
-struct Program {
-    memory: Vec<i32>,
-    ip: usize,
-}
-
-impl Program {
-    fn new(integers: &[i32]) -> Program {
-        Program {
-            memory: integers.to_vec(),
-            ip: 0,
-        }
-    }
-
-    fn run(&mut self, input: i32) -> bool {
-        let opcode = self.memory[self.ip] % 100;
-
-        match opcode {
-            1 => {
-                // Add
-                let parameter_1_mode = (self.memory[self.ip] / 100) & 1;
-                let parameter_2_mode = (self.memory[self.ip] / 1000) & 1;
-
-                let parameter_1 = self.memory[self.ip + 1];
-                let parameter_2 = self.memory[self.ip + 2];
-                let parameter_3 = self.memory[self.ip + 3];
-                self.ip += 4;
-
-                let value_1 = match parameter_1_mode {
-                    0 => self.memory[parameter_1 as usize],
-                    1 => parameter_1,
-                    _ => panic!("Unknown parameter mode {}", parameter_1_mode),
-                };
-                let value_2 = match parameter_2_mode {
-                    0 => self.memory[parameter_2 as usize],
-                    1 => parameter_2,
-                    _ => panic!("Unknown parameter mode {}", parameter_2_mode),
-                };
-
-                self.memory[parameter_3 as usize] = value_1 + value_2;
-
-                true
-            }
-            2 => {
-                // Multiply
-                let parameter_1_mode = (self.memory[self.ip] / 100) & 1;
-                let parameter_2_mode = (self.memory[self.ip] / 1000) & 1;
-
-                let parameter_1 = self.memory[self.ip + 1];
-                let parameter_2 = self.memory[self.ip + 2];
-                let parameter_3 = self.memory[self.ip + 3];
-                self.ip += 4;
-
-                let value_1 = match parameter_1_mode {
-                    0 => self.memory[parameter_1 as usize],
-                    1 => parameter_1,
-                    _ => panic!("Unknown parameter mode {}", parameter_1_mode),
-                };
-                let value_2 = match parameter_2_mode {
-                    0 => self.memory[parameter_2 as usize],
-                    1 => parameter_2,
-                    _ => panic!("Unknown parameter mode {}", parameter_2_mode),
-                };
-
-                self.memory[parameter_3 as usize] = value_1 * value_2;
-
-                true
-            }
-            3 => {
-                // Input
-                let parameter_1 = self.memory[self.ip + 1];
-                self.ip += 2;
-
-                self.memory[parameter_1 as usize] = input;
-
-                true
-            }
-            4 => {
-                // Output
-                let parameter_1_mode = (self.memory[self.ip] / 100) & 1;
-
-                let parameter_1 = self.memory[self.ip + 1];
-                self.ip += 2;
-
-                let value_1 = match parameter_1_mode {
-                    0 => self.memory[parameter_1 as usize],
-                    1 => parameter_1,
-                    _ => panic!("Unknown parameter mode {}", parameter_1_mode),
-                };
-
-                println!("{}", value_1);
-
-                true
-            }
-            5 => {
-                // Jump if true
-                let parameter_1_mode = (self.memory[self.ip] / 100) & 1;
-                let parameter_2_mode = (self.memory[self.ip] / 1000) & 1;
-
-                let parameter_1 = self.memory[self.ip + 1];
-                let parameter_2 = self.memory[self.ip + 2];
-                self.ip += 3;
-
-                let value_1 = match parameter_1_mode {
-                    0 => self.memory[parameter_1 as usize],
-                    1 => parameter_1,
-                    _ => panic!("Unknown parameter mode {}", parameter_1_mode),
-                };
-                let value_2 = match parameter_2_mode {
-                    0 => self.memory[parameter_2 as usize],
-                    1 => parameter_2,
-                    _ => panic!("Unknown parameter mode {}", parameter_2_mode),
-                };
-
-                if value_1 != 0 {
-                    self.ip = value_2 as usize;
-                }
-
-                true
-            }
-            6 => {
-                // Jump if false
-                let parameter_1_mode = (self.memory[self.ip] / 100) & 1;
-                let parameter_2_mode = (self.memory[self.ip] / 1000) & 1;
-
-                let parameter_1 = self.memory[self.ip + 1];
-                let parameter_2 = self.memory[self.ip + 2];
-                self.ip += 3;
-
-                let value_1 = match parameter_1_mode {
-                    0 => self.memory[parameter_1 as usize],
-                    1 => parameter_1,
-                    _ => panic!("Unknown parameter mode {}", parameter_1_mode),
-                };
-                let value_2 = match parameter_2_mode {
-                    0 => self.memory[parameter_2 as usize],
-                    1 => parameter_2,
-                    _ => panic!("Unknown parameter mode {}", parameter_2_mode),
-                };
-
-                if value_1 == 0 {
-                    self.ip = value_2 as usize;
-                }
-
-                true
-            }
-            7 => {
-                // Less than
-                let parameter_1_mode = (self.memory[self.ip] / 100) & 1;
-                let parameter_2_mode = (self.memory[self.ip] / 1000) & 1;
-
-                let parameter_1 = self.memory[self.ip + 1];
-                let parameter_2 = self.memory[self.ip + 2];
-                let parameter_3 = self.memory[self.ip + 3];
-                self.ip += 4;
-
-                let value_1 = match parameter_1_mode {
-                    0 => self.memory[parameter_1 as usize],
-                    1 => parameter_1,
-                    _ => panic!("Unknown parameter mode {}", parameter_1_mode),
-                };
-                let value_2 = match parameter_2_mode {
-                    0 => self.memory[parameter_2 as usize],
-                    1 => parameter_2,
-                    _ => panic!("Unknown parameter mode {}", parameter_2_mode),
-                };
-
-                self.memory[parameter_3 as usize] = if value_1 < value_2 { 1 } else { 0 };
-
-                true
-            }
-            8 => {
-                // Equal
-                let parameter_1_mode = (self.memory[self.ip] / 100) & 1;
-                let parameter_2_mode = (self.memory[self.ip] / 1000) & 1;
-
-                let parameter_1 = self.memory[self.ip + 1];
-                let parameter_2 = self.memory[self.ip + 2];
-                let parameter_3 = self.memory[self.ip + 3];
-                self.ip += 4;
-
-                let value_1 = match parameter_1_mode {
-                    0 => self.memory[parameter_1 as usize],
-                    1 => parameter_1,
-                    _ => panic!("Unknown parameter mode {}", parameter_1_mode),
-                };
-                let value_2 = match parameter_2_mode {
-                    0 => self.memory[parameter_2 as usize],
-                    1 => parameter_2,
-                    _ => panic!("Unknown parameter mode {}", parameter_2_mode),
-                };
-
-                self.memory[parameter_3 as usize] = if value_1 == value_2 { 1 } else { 0 };
+fn run(integers: &[i64], input: i64) {
+    let mut computer = Computer::new(integers, VecDeque::from([input]), Vec::new(), NoTrace);
+    computer.run().unwrap();
 
-                true
-            }
-            99 => false,
-            _ => {
-                unimplemented!();
-            }
-        }
+    for value in computer.output() {
+        println!("{}", value);
     }
 }
 
@@ -208,16 +15,11 @@ fn main() {
     let mut input = String::new();
     stdin().read_to_string(&mut input).unwrap();
 
-    let integers: Vec<i32> = input
-        .split(',')
-        .map(|x| x.trim().parse::<i32>().unwrap())
-        .collect();
+    let integers = parse_program(&input).unwrap();
 
     // Part 1
-    let mut program = Program::new(&integers);
-    while program.run(1) {}
+    run(&integers, 1);
 
     // Part 2
-    let mut program = Program::new(&integers);
-    while program.run(5) {}
+    run(&integers, 5);
 }
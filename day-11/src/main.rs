@@ -1,10 +1,8 @@
-mod computer;
-
-use computer::Computer;
-use std::collections::HashSet;
+use intcode::{parse_program, Computer, NoTrace, Session, StepResult};
+use std::collections::{HashSet, VecDeque};
+use std::env;
 use std::io::{stdin, Read};
-use std::sync::mpsc::channel;
-use std::thread;
+use std::process;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Position {
@@ -20,37 +18,28 @@ enum Orientation {
     West,
 }
 
-fn main() {
-    let mut input = String::new();
-    stdin().read_to_string(&mut input).unwrap();
-
-    let program: Vec<i64> = input
-        .split(',')
-        .map(|x| x.trim().parse::<i64>().unwrap())
-        .collect();
-
-    // Part 1
-
-    let (tx_computer, rx_computer) = channel();
-    let (tx_master, rx_master) = channel();
-
-    let mut computer = Computer::new(&program, rx_computer, tx_master, false);
-    thread::spawn(move || computer.run());
+// Runs the robot from a panel of the given colour until it halts, and
+// returns the panels left white and the number painted at least once
+fn paint(program: &[i64], start: i64, session: &Session) -> (HashSet<Position>, usize) {
+    let mut computer = Computer::new(program, VecDeque::new(), Vec::new(), NoTrace);
+    session.start(&mut computer).unwrap();
 
     let mut whites = HashSet::new();
     let mut painted_positions = HashSet::new();
     let mut position = Position { x: 0, y: 0 };
     let mut orientation = Orientation::North;
 
+    if start == 1 {
+        whites.insert(position);
+    }
+
     loop {
         let mut color = if whites.contains(&position) { 1 } else { 0 };
-        if tx_computer.send(color).is_err() {
-            break;
-        }
+        computer.input_mut().push_back(color);
 
-        color = match rx_master.recv() {
-            Ok(new_color) => new_color,
-            Err(_) => break,
+        color = match computer.run_until_io().unwrap() {
+            StepResult::Output(new_color) => new_color,
+            _ => break,
         };
         match color {
             0 => whites.remove(&position),
@@ -59,9 +48,9 @@ fn main() {
         };
         painted_positions.insert(position);
 
-        let turn = match rx_master.recv() {
-            Ok(turn) => turn,
-            Err(_) => break,
+        let turn = match computer.run_until_io().unwrap() {
+            StepResult::Output(turn) => turn,
+            _ => break,
         };
         orientation = match (turn, &orientation) {
             (0, Orientation::North) => Orientation::West,
@@ -82,101 +71,69 @@ fn main() {
         }
     }
 
-    println!(
-        "Part 1: {} panels are painted at least once",
-        painted_positions.len()
-    );
-
-    if false {
-        let min_x = whites.iter().map(|position| position.x).min().unwrap();
-        let max_x = whites.iter().map(|position| position.x).max().unwrap();
-        let min_y = whites.iter().map(|position| position.y).min().unwrap();
-        let max_y = whites.iter().map(|position| position.y).max().unwrap();
-        for y in min_y..=max_y {
-            for x in min_x..=max_x {
-                print!(
-                    "{}",
-                    if whites.contains(&Position { x, y }) {
-                        '#'
-                    } else {
-                        '.'
-                    }
-                );
-            }
-            println!();
-        }
-    }
+    session.finish(&mut computer).unwrap();
+    (whites, painted_positions.len())
+}
 
-    // Part 2
+fn main() {
+    // The painting in part 1 can be recorded to a transcript, or replayed
+    // from one to check that the program still behaves the same
+    let session = Session::from_args(env::args().skip(1)).unwrap_or_else(|| {
+        eprintln!("usage: day-11 [--record FILE | --replay FILE] < program");
+        process::exit(1);
+    });
 
-    let (tx_computer, rx_computer) = channel();
-    let (tx_master, rx_master) = channel();
+    let mut input = String::new();
+    stdin().read_to_string(&mut input).unwrap();
 
-    let mut computer = Computer::new(&program, rx_computer, tx_master, false);
-    thread::spawn(move || computer.run());
+    let program = parse_program(&input).unwrap();
 
-    let mut whites = HashSet::new();
-    let mut position = Position { x: 0, y: 0 };
-    let mut orientation = Orientation::North;
-
-    whites.insert(position);
+    // Part 1
 
-    loop {
-        let mut color = if whites.contains(&position) { 1 } else { 0 };
-        if tx_computer.send(color).is_err() {
-            break;
-        }
+    let (_, painted) = paint(&program, 0, &session);
+    println!("Part 1: {} panels are painted at least once", painted);
 
-        color = match rx_master.recv() {
-            Ok(new_color) => new_color,
-            Err(_) => break,
-        };
-        match color {
-            0 => whites.remove(&position),
-            1 => whites.insert(position),
-            _ => panic!("Received unexpected color {}", color),
-        };
+    // Part 2
 
-        let turn = match rx_master.recv() {
-            Ok(turn) => turn,
-            Err(_) => break,
-        };
-        orientation = match (turn, &orientation) {
-            (0, Orientation::North) => Orientation::West,
-            (1, Orientation::North) => Orientation::East,
-            (0, Orientation::East) => Orientation::North,
-            (1, Orientation::East) => Orientation::South,
-            (0, Orientation::South) => Orientation::East,
-            (1, Orientation::South) => Orientation::West,
-            (0, Orientation::West) => Orientation::South,
-            (1, Orientation::West) => Orientation::North,
-            _ => panic!("Unexpected turn {} and orientation {:?}", turn, orientation),
-        };
-        match orientation {
-            Orientation::North => position.y -= 1,
-            Orientation::East => position.x += 1,
-            Orientation::South => position.y += 1,
-            Orientation::West => position.x -= 1,
+    let (whites, _) = paint(&program, 1, &Session::Live);
+
+    let min_x = whites.iter().map(|position| position.x).min().unwrap();
+    let max_x = whites.iter().map(|position| position.x).max().unwrap();
+    let min_y = whites.iter().map(|position| position.y).min().unwrap();
+    let max_y = whites.iter().map(|position| position.y).max().unwrap();
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            print!(
+                "{}",
+                if whites.contains(&Position { x, y }) {
+                    '#'
+                } else {
+                    '.'
+                }
+            );
         }
+        println!();
     }
+}
 
-    if true {
-        let min_x = whites.iter().map(|position| position.x).min().unwrap();
-        let max_x = whites.iter().map(|position| position.x).max().unwrap();
-        let min_y = whites.iter().map(|position| position.y).min().unwrap();
-        let max_y = whites.iter().map(|position| position.y).max().unwrap();
-        for y in min_y..=max_y {
-            for x in min_x..=max_x {
-                print!(
-                    "{}",
-                    if whites.contains(&Position { x, y }) {
-                        '#'
-                    } else {
-                        '.'
-                    }
-                );
-            }
-            println!();
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::assemble;
+    use std::fs;
+
+    // Paints with the stand-in robot program against a golden transcript
+    // of part 1. After a change to the program, record a new one with
+    //
+    //     cargo run --manifest-path ../intcode/Cargo.toml --bin assemble < tests/robot.asm \
+    //         | cargo run -- --record tests/robot.transcript
+    #[test]
+    fn robot_golden_transcript() {
+        let program = assemble(&fs::read_to_string("tests/robot.asm").unwrap()).unwrap();
+
+        let session = Session::Replay("tests/robot.transcript".into());
+        let (whites, painted) = paint(&program, 0, &session);
+        assert_eq!(painted, 6);
+        assert_eq!(whites.len(), 5);
     }
 }
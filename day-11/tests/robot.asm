@@ -0,0 +1,16 @@
+; A stand-in for the hull painting robot. It paints each panel the
+; opposite colour to the one it reads and turns as the robot in the
+; puzzle's example does, which leaves 6 panels painted.
+        arb  #turns
+loop:   in   color
+        mul  color #-1 color
+        add  color #1 color
+        out  color
+        out  @0
+        arb  #1
+        add  count #-1 count
+        jt   count #loop
+        hlt
+color:  data 0
+count:  data 7
+turns:  data 0 0 0 0 1 0 0
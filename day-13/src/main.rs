@@ -1,12 +1,10 @@
-mod computer;
-
-use computer::Computer;
+use intcode::{parse_program, Computer, NoTrace, Session, StepResult};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::env;
 use std::fmt;
 use std::io::{stdin, Read};
-use std::sync::mpsc::channel;
-use std::thread;
+use std::process;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 struct Position {
@@ -68,10 +66,7 @@ impl fmt::Display for Game {
 
 impl Game {
     fn new(input: &str) -> Game {
-        let program: Vec<i64> = input
-            .split(',')
-            .map(|x| x.trim().parse::<i64>().unwrap())
-            .collect();
+        let program = parse_program(input).unwrap();
         let screen = HashMap::new();
         let score = 0;
         let paddle = Position::new(0, 0);
@@ -90,32 +85,29 @@ impl Game {
         self.program[0] = 2;
     }
 
-    fn run(&mut self, play: bool) {
-        let (tx_computer, rx_computer) = channel();
-        let (tx_master, rx_master) = channel();
-
-        let mut computer = Computer::new(&self.program, rx_computer, tx_master, false);
-        thread::spawn(move || computer.run());
+    fn run(&mut self, play: bool, session: &Session) {
+        let mut computer = Computer::new(&self.program, VecDeque::new(), Vec::new(), NoTrace);
+        session.start(&mut computer).unwrap();
 
         loop {
             let mut redraw = false;
 
-            let x = match rx_master.recv() {
-                Ok(integer) => integer as isize,
-                Err(_) => break,
+            let x = match computer.run_until_io().unwrap() {
+                StepResult::Output(integer) => integer as isize,
+                _ => break,
             };
-            let y = match rx_master.recv() {
-                Ok(integer) => integer as isize,
-                Err(_) => break,
+            let y = match computer.run_until_io().unwrap() {
+                StepResult::Output(integer) => integer as isize,
+                _ => break,
             };
             if x == -1 && y == 0 {
-                self.score = match rx_master.recv() {
-                    Ok(integer) => integer,
-                    Err(_) => break,
+                self.score = match computer.run_until_io().unwrap() {
+                    StepResult::Output(integer) => integer,
+                    _ => break,
                 };
             } else {
-                let tile = match rx_master.recv() {
-                    Ok(integer) => match integer {
+                let tile = match computer.run_until_io().unwrap() {
+                    StepResult::Output(integer) => match integer {
                         0 => Tile::Empty,
                         1 => Tile::Wall,
                         2 => Tile::Block,
@@ -123,7 +115,7 @@ impl Game {
                         4 => Tile::Ball,
                         _ => panic!("Unexpected tile {}", integer),
                     },
-                    Err(_) => break,
+                    _ => break,
                 };
 
                 self.screen.insert(Position::new(x, y), tile);
@@ -141,10 +133,7 @@ impl Game {
                             Ordering::Equal => 0,
                             Ordering::Greater => -1,
                         };
-                        match tx_computer.send(joystick) {
-                            Ok(_) => (),
-                            Err(_) => break,
-                        }
+                        computer.input_mut().push_back(joystick);
                     }
                     _ => (),
                 }
@@ -154,6 +143,8 @@ impl Game {
                 println!("{}", self);
             }
         }
+
+        session.finish(&mut computer).unwrap();
     }
 
     fn block_count(&self) -> usize {
@@ -164,43 +155,30 @@ impl Game {
     }
 
     fn screen_extents(&self) -> (isize, isize, isize, isize) {
-        let x_min = self
-            .screen
-            .iter()
-            .map(|(position, _)| position.x)
-            .min()
-            .unwrap();
-        let x_max = self
-            .screen
-            .iter()
-            .map(|(position, _)| position.x)
-            .max()
-            .unwrap();
-        let y_min = self
-            .screen
-            .iter()
-            .map(|(position, _)| position.y)
-            .min()
-            .unwrap();
-        let y_max = self
-            .screen
-            .iter()
-            .map(|(position, _)| position.y)
-            .max()
-            .unwrap();
+        let x_min = self.screen.keys().map(|position| position.x).min().unwrap();
+        let x_max = self.screen.keys().map(|position| position.x).max().unwrap();
+        let y_min = self.screen.keys().map(|position| position.y).min().unwrap();
+        let y_max = self.screen.keys().map(|position| position.y).max().unwrap();
 
         (x_min, x_max, y_min, y_max)
     }
 }
 
 fn main() {
+    // The game played in part 2 can be recorded to a transcript, or
+    // replayed from one to check that the program still plays it the same
+    let session = Session::from_args(env::args().skip(1)).unwrap_or_else(|| {
+        eprintln!("usage: day-13 [--record FILE | --replay FILE] < program");
+        process::exit(1);
+    });
+
     let mut input = String::new();
     stdin().read_to_string(&mut input).unwrap();
 
     // Part 1
 
     let mut game = Game::new(&input);
-    game.run(false);
+    game.run(false, &Session::Live);
     println!(
         "Part 1: there are {} block tiles on the screen",
         game.block_count()
@@ -210,6 +188,35 @@ fn main() {
 
     let mut game = Game::new(&input);
     game.insert_quarters();
-    game.run(false);
+    game.run(false, &session);
     println!("Part 2: the final score is {}", game.score);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::assemble;
+    use std::fs;
+
+    // Plays the stand-in arcade program against a golden transcript of
+    // part 2. After a change to the program, record a new one with
+    //
+    //     cargo run --manifest-path ../intcode/Cargo.toml --bin assemble < tests/arcade.asm \
+    //         | cargo run -- --record tests/arcade.transcript
+    #[test]
+    fn arcade_golden_transcript() {
+        let program = assemble(&fs::read_to_string("tests/arcade.asm").unwrap()).unwrap();
+        let input: Vec<String> = program.iter().map(i64::to_string).collect();
+        let input = input.join(",");
+
+        let mut game = Game::new(&input);
+        game.run(false, &Session::Live);
+        assert_eq!(game.block_count(), 6);
+
+        let mut game = Game::new(&input);
+        game.insert_quarters();
+        game.run(false, &Session::Replay("tests/arcade.transcript".into()));
+        assert_eq!(game.score, 270);
+        assert_eq!(game.block_count(), 0);
+    }
+}
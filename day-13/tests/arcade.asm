@@ -0,0 +1,75 @@
+; A stand-in for the arcade cabinet. Cell 0 is 1 for a demo, which draws
+; the screen and halts, and 2 once quarters are inserted, which plays: the
+; ball bounces along a row and each time the paddle is under it the next
+; block breaks, until none are left.
+        add  one one demo       ; mul once quarters are in, giving 1
+        out  #0                 ; walls
+        out  #0
+        out  #1
+        out  #9
+        out  #0
+        out  #1
+draw:   out  block              ; blocks at x = 2 to 7 on row 2
+        out  #2
+        out  #2
+        add  block #1 block
+        lt   block #8 more
+        jt   more #draw
+        add  #2 #0 block
+        out  paddle
+        out  #5
+        out  #3
+        out  ball
+        out  #4
+        out  #4
+        eq   demo #2 more
+        jt   more #done
+
+frame:  out  ball               ; clear the ball and move it
+        out  #4
+        out  #0
+        add  ball dx ball
+        eq   ball #1 more
+        jt   more #bounce
+        eq   ball #8 more
+        jf   more #moved
+bounce: mul  dx #-1 dx
+moved:  out  ball
+        out  #4
+        out  #4
+        in   joy                ; clear the paddle and move it
+        out  paddle
+        out  #5
+        out  #0
+        add  paddle joy paddle
+        out  paddle
+        out  #5
+        out  #3
+        eq   paddle ball more
+        jf   more #count
+        out  block              ; break the next block and score it
+        out  #2
+        out  #0
+        mul  block #10 points
+        add  score points score
+        out  #-1
+        out  #0
+        out  score
+        add  block #1 block
+        eq   block #8 more
+        jt   more #done
+count:  add  frames #-1 frames
+        jt   frames #frame
+done:   hlt
+
+one:    data 1
+demo:   data 0
+block:  data 2
+more:   data 0
+paddle: data 4
+ball:   data 1
+dx:     data 1
+joy:    data 0
+points: data 0
+score:  data 0
+frames: data 200
@@ -1,12 +1,10 @@
-mod computer;
-
-use computer::Computer;
+use intcode::{parse_program, Computer, NoTrace, Session, StepResult};
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
 use std::fmt;
 use std::io::{stdin, Read};
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::thread;
+use std::process;
 
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
 enum Direction {
@@ -112,30 +110,10 @@ impl Map {
     }
 
     fn extents(&self) -> (isize, isize, isize, isize) {
-        let x_min = self
-            .places
-            .iter()
-            .map(|(position, _place)| position.x)
-            .min()
-            .unwrap();
-        let x_max = self
-            .places
-            .iter()
-            .map(|(position, _place)| position.x)
-            .max()
-            .unwrap();
-        let y_min = self
-            .places
-            .iter()
-            .map(|(position, _place)| position.y)
-            .min()
-            .unwrap();
-        let y_max = self
-            .places
-            .iter()
-            .map(|(position, _place)| position.y)
-            .max()
-            .unwrap();
+        let x_min = self.places.keys().map(|position| position.x).min().unwrap();
+        let x_max = self.places.keys().map(|position| position.x).max().unwrap();
+        let y_min = self.places.keys().map(|position| position.y).min().unwrap();
+        let y_max = self.places.keys().map(|position| position.y).max().unwrap();
 
         (x_min, x_max, y_min, y_max)
     }
@@ -174,25 +152,19 @@ impl Map {
 }
 
 struct Explorer {
-    tx_computer: Sender<i64>,
-    rx_master: Receiver<i64>,
+    computer: Computer<VecDeque<i64>, Vec<i64>>,
     bias: Direction,
     map: Map,
 }
 
 impl Explorer {
     fn new(program: &[i64], bias: Direction) -> Explorer {
-        let (tx_computer, rx_computer) = channel();
-        let (tx_master, rx_master) = channel();
-
-        let mut computer = Computer::new(program, rx_computer, tx_master, false);
-        thread::spawn(move || computer.run());
+        let computer = Computer::new(program, VecDeque::new(), Vec::new(), NoTrace);
 
         let map = Map::new();
 
         Explorer {
-            tx_computer,
-            rx_master,
+            computer,
             bias,
             map,
         }
@@ -204,13 +176,11 @@ impl Explorer {
         let mut target = position.neighbor(direction);
 
         loop {
-            self.tx_computer
-                .send(direction as i64)
-                .expect("Failed send to computer");
+            self.computer.input_mut().push_back(direction as i64);
 
-            let status = match self.rx_master.recv() {
-                Ok(integer) => integer,
-                Err(_) => panic!("Failed receive from computer"),
+            let status = match self.computer.run_until_io().unwrap() {
+                StepResult::Output(integer) => integer,
+                step_result => panic!("Unexpected {:?} from computer", step_result),
             };
 
             match status {
@@ -240,8 +210,6 @@ impl Explorer {
                 _ => panic!("Unexpected status {} received from computer", status),
             }
         }
-
-        self.tx_computer.send(99).expect("Failed send to computer");
     }
 
     fn choose_direction(&mut self, position: Position) -> Direction {
@@ -308,11 +276,8 @@ impl Explorer {
                 criteria.push((visits, neighbor));
             }
 
-            criteria = criteria
-                .into_iter()
-                .filter(|&criterion| !path.contains(&criterion.1))
-                .collect();
-            criteria.sort_by(|a, b| a.0.cmp(&b.0));
+            criteria.retain(|&criterion| !path.contains(&criterion.1));
+            criteria.sort_by_key(|a| a.0);
 
             position = criteria[0].1;
             path.push(position);
@@ -328,18 +293,24 @@ impl Explorer {
 }
 
 fn main() {
+    // The exploration in part 1 can be recorded to a transcript, or
+    // replayed from one to check that the program still behaves the same
+    let session = Session::from_args(env::args().skip(1)).unwrap_or_else(|| {
+        eprintln!("usage: day-15 [--record FILE | --replay FILE] < program");
+        process::exit(1);
+    });
+
     let mut input = String::new();
     stdin().read_to_string(&mut input).unwrap();
 
-    let program: Vec<i64> = input
-        .split(',')
-        .map(|x| x.trim().parse::<i64>().unwrap())
-        .collect();
+    let program = parse_program(&input).unwrap();
 
     // Part 1
 
     let mut explorer = Explorer::new(&program, Direction::North);
+    session.start(&mut explorer.computer).unwrap();
     explorer.explore();
+    session.finish(&mut explorer.computer).unwrap();
     println!(
         "Part 1: the fewest number of movement commands is {}",
         explorer.fewest_movement_commands()
@@ -382,3 +353,28 @@ fn main() {
         minutes
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::assemble;
+    use std::fs;
+
+    // Explores with the stand-in droid program against a golden transcript
+    // of part 1. After a change to the program, record a new one with
+    //
+    //     cargo run --manifest-path ../intcode/Cargo.toml --bin assemble < tests/droid.asm \
+    //         | cargo run -- --record tests/droid.transcript
+    #[test]
+    fn droid_golden_transcript() {
+        let program = assemble(&fs::read_to_string("tests/droid.asm").unwrap()).unwrap();
+
+        let session = Session::Replay("tests/droid.transcript".into());
+        let mut explorer = Explorer::new(&program, Direction::North);
+        session.start(&mut explorer.computer).unwrap();
+        explorer.explore();
+        session.finish(&mut explorer.computer).unwrap();
+        assert_eq!(explorer.fewest_movement_commands(), 7);
+        assert_eq!(explorer.map.oxygen_system(), Position::new(4, 3));
+    }
+}
@@ -0,0 +1,32 @@
+; A stand-in for the repair droid, in a small maze kept as a grid of
+; cells 7 wide: 0 for a wall, 1 for open and 2 for the oxygen system. The
+; droid starts in the top left open cell, and the shortest route to the
+; oxygen system takes 7 moves.
+loop:   in   dir
+        arb  dir                ; step = offsets[dir]
+        add  @offsets #0 step
+        mul  dir #-1 back
+        arb  back
+        add  pos step target
+        arb  target             ; cell = grid[target]
+        add  @grid #0 cell
+        mul  target #-1 back
+        arb  back
+        jf   cell #report
+        add  target #0 pos
+report: out  cell
+        jt   #1 #loop
+dir:    data 0
+step:   data 0
+back:   data 0
+pos:    data 8
+target: data 0
+cell:   data 0
+offsets: data 0 -7 7 -1 1
+grid:   data 0 0 0 0 0 0 0
+        data 0 1 1 1 0 1 0
+        data 0 1 0 1 0 1 0
+        data 0 1 0 1 1 1 0
+        data 0 1 0 0 0 2 0
+        data 0 1 1 1 1 1 0
+        data 0 0 0 0 0 0 0
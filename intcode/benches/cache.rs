@@ -0,0 +1,131 @@
+// Compares running with and without the decoded instruction cache, on the
+// stand-ins for the day-09 part 2 and day-13 part 2 workloads in
+// tests/programs, plus the real programs if given on the command line:
+//
+//     cargo bench --bench cache -- [day-09 program file [day-13 program file]]
+
+use intcode::{assemble, parse_program, Computer, NoTrace, StepResult};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+const ROUNDS: usize = 5;
+
+// Fibonacci by naive recursion, and the arcade cabinet's game loop, as
+// the transpiler tests also run them
+const RECURSION: &str = include_str!("../tests/programs/day-09-recursion.asm");
+const ARCADE: &str = include_str!("../tests/programs/day-13.asm");
+
+// The arcade program with its frame count, held in its last cell, replaced
+fn arcade(frames: i64) -> Vec<i64> {
+    let mut program = assemble(ARCADE).unwrap();
+    *program.last_mut().unwrap() = frames;
+    program
+}
+
+fn next_output(computer: &mut Computer<VecDeque<i64>, Vec<i64>>) -> i64 {
+    match computer.run_until_io().unwrap() {
+        StepResult::Output(value) => value,
+        step_result => panic!("expected an output, not {:?}", step_result),
+    }
+}
+
+// Plays the game to the end, steering the paddle towards the ball, and
+// returns the final score
+fn play(computer: &mut Computer<VecDeque<i64>, Vec<i64>>) -> Vec<i64> {
+    let (mut paddle, mut ball, mut score) = (0, 0, 0);
+
+    loop {
+        match computer.run_until_io().unwrap() {
+            StepResult::NeedsInput => {
+                let joystick = match paddle.cmp(&ball) {
+                    Ordering::Less => 1,
+                    Ordering::Equal => 0,
+                    Ordering::Greater => -1,
+                };
+                computer.input_mut().push_back(joystick);
+            }
+            StepResult::Output(x) => {
+                let y = next_output(computer);
+                let tile = next_output(computer);
+                match (x, y, tile) {
+                    (-1, 0, _) => score = tile,
+                    (_, _, 3) => paddle = x,
+                    (_, _, 4) => ball = x,
+                    _ => (),
+                }
+            }
+            StepResult::Halted => return vec![score],
+        }
+    }
+}
+
+fn time<F>(program: &[i64], cache: bool, run: F) -> (Duration, Vec<i64>)
+where
+    F: Fn(&mut Computer<VecDeque<i64>, Vec<i64>>) -> Vec<i64>,
+{
+    let mut best = Duration::MAX;
+    let mut output = Vec::new();
+
+    for _ in 0..ROUNDS {
+        let mut computer = Computer::new(program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_decode_cache(cache);
+
+        let start = Instant::now();
+        output = run(&mut computer);
+        best = best.min(start.elapsed());
+    }
+
+    (best, output)
+}
+
+fn compare<F>(name: &str, program: &[i64], run: F)
+where
+    F: Fn(&mut Computer<VecDeque<i64>, Vec<i64>>) -> Vec<i64>,
+{
+    let (uncached, uncached_output) = time(program, false, &run);
+    let (cached, cached_output) = time(program, true, &run);
+    assert_eq!(uncached_output, cached_output, "{}: outputs differ", name);
+
+    println!(
+        "{:<24} uncached {:>10.3?}   cached {:>10.3?}   speedup {:>5.2}x",
+        name,
+        uncached,
+        cached,
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
+
+// Runs a program to the end with a single input value
+fn run_with(value: i64) -> impl Fn(&mut Computer<VecDeque<i64>, Vec<i64>>) -> Vec<i64> {
+    move |computer| {
+        computer.input_mut().push_back(value);
+        computer.run().unwrap();
+        computer.output().clone()
+    }
+}
+
+fn read_program(path: &str) -> Vec<i64> {
+    parse_program(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn main() {
+    let args: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+
+    compare("recursion", &assemble(RECURSION).unwrap(), run_with(25));
+    compare("arcade", &arcade(200_000), play);
+
+    if let Some(path) = args.first() {
+        compare("day-09 part 2", &read_program(path), run_with(2));
+    }
+    if let Some(path) = args.get(1) {
+        let mut program = read_program(path);
+        program[0] = 2;
+        compare("day-13 part 2", &program, play);
+    }
+}
@@ -0,0 +1,90 @@
+// Compares PagedMemory against the original HashMap layout, kept as
+// SparseMemory. Runs two built-in loops, plus any program given on the
+// command line:
+//
+//     cargo bench --bench memory -- <program file> [input]...
+
+use intcode::{parse_program, Computer, Memory, NoTrace, PagedMemory, SparseMemory};
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+const ROUNDS: usize = 5;
+
+// Adds each counter value to an accumulator, using position mode only
+fn counting_loop(iterations: i64) -> Vec<i64> {
+    vec![
+        1001, 100, 1, 100, // add [100] #1 -> [100]
+        1, 100, 101, 101, // add [100] [101] -> [101]
+        1007, 100, iterations, 102, // lt [100] #iterations -> [102]
+        1005, 102, 0, // jt [102] #0
+        4, 101, // out [101]
+        99,
+    ]
+}
+
+// Walks the relative base upwards, writing a fresh cell and reading back
+// the previous one on each iteration
+fn relative_walk(iterations: i64) -> Vec<i64> {
+    vec![
+        109, 1, // arb #1
+        21001, 100, 7, 1000, // add [100] #7 -> [rb+1000]
+        201, 999, 101, 101, // add [rb+999] [101] -> [101]
+        1001, 100, 1, 100, // add [100] #1 -> [100]
+        1007, 100, iterations, 102, // lt [100] #iterations -> [102]
+        1005, 102, 0, // jt [102] #0
+        4, 101, // out [101]
+        99,
+    ]
+}
+
+fn time<M: Memory>(program: &[i64], input: &[i64]) -> (Duration, Vec<i64>) {
+    let mut best = Duration::MAX;
+    let mut output = Vec::new();
+
+    for _ in 0..ROUNDS {
+        let memory = M::from_program(program);
+        let mut computer =
+            Computer::with_memory(memory, VecDeque::from(input.to_vec()), Vec::new(), NoTrace);
+
+        let start = Instant::now();
+        computer.run().unwrap();
+        best = best.min(start.elapsed());
+
+        output = computer.into_io().1;
+    }
+
+    (best, output)
+}
+
+fn compare(name: &str, program: &[i64], input: &[i64]) {
+    let (sparse, sparse_output) = time::<SparseMemory>(program, input);
+    let (paged, paged_output) = time::<PagedMemory>(program, input);
+    assert_eq!(sparse_output, paged_output, "{}: outputs differ", name);
+
+    println!(
+        "{:<24} HashMap {:>10.3?}   paged {:>10.3?}   speedup {:>5.2}x",
+        name,
+        sparse,
+        paged,
+        sparse.as_secs_f64() / paged.as_secs_f64()
+    );
+}
+
+fn main() {
+    let args: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+
+    compare("counting loop", &counting_loop(1_000_000), &[]);
+    compare("relative walk", &relative_walk(1_000_000), &[]);
+
+    if let Some((path, input)) = args.split_first() {
+        let program = parse_program(&fs::read_to_string(path).unwrap()).unwrap();
+        let input: Vec<i64> = input.iter().map(|x| x.parse::<i64>().unwrap()).collect();
+
+        compare(path, &program, &input);
+    }
+}
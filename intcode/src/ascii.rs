@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::computer::{Computer, StepResult};
+use crate::error::IntcodeError;
+use crate::memory::{Memory, PagedMemory};
+use crate::trace::NoTrace;
+
+// What an ASCII program wrote: a line of text without its newline, or a
+// value outside the ASCII range, such as a puzzle answer
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AsciiOutput {
+    Line(String),
+    Value(i64),
+}
+
+impl fmt::Display for AsciiOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiOutput::Line(line) => write!(f, "{}", line),
+            AsciiOutput::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// Drives a program which reads and writes ASCII codes, one line at a time
+pub struct Ascii<M = PagedMemory> {
+    computer: Computer<VecDeque<i64>, Vec<i64>, M>,
+    line: String,
+    halted: bool,
+}
+
+impl Ascii {
+    pub fn new(program: &[i64]) -> Ascii {
+        Ascii::from_computer(Computer::new(program, VecDeque::new(), Vec::new(), NoTrace))
+    }
+}
+
+impl<M: Memory> Ascii<M> {
+    pub fn from_computer(computer: Computer<VecDeque<i64>, Vec<i64>, M>) -> Ascii<M> {
+        Ascii {
+            computer,
+            line: String::new(),
+            halted: false,
+        }
+    }
+
+    // Queues text as ASCII codes, with Windows line endings turned into
+    // plain newlines. Text with a character outside ASCII is refused whole,
+    // rather than sent as codes the program cannot read.
+    pub fn send(&mut self, text: &str) -> Result<(), IntcodeError> {
+        if let Some(character) = text.chars().find(|c| !c.is_ascii()) {
+            return Err(IntcodeError::NonAsciiInput { character });
+        }
+
+        let text = text.replace("\r\n", "\n");
+        self.computer
+            .input_mut()
+            .extend(text.bytes().map(i64::from));
+        Ok(())
+    }
+
+    // Queues a line of text followed by a newline
+    pub fn send_line(&mut self, line: &str) -> Result<(), IntcodeError> {
+        self.send(line)?;
+        self.send("\n")
+    }
+
+    // Runs until the program has read all the text sent to it and wants
+    // more, or halts, and returns what it wrote. A line the program has
+    // not finished when it stops, such as a prompt, is returned as it is.
+    pub fn run(&mut self) -> Result<Vec<AsciiOutput>, IntcodeError> {
+        let mut outputs = Vec::new();
+
+        loop {
+            match self.computer.run_until_io()? {
+                StepResult::Output(value) => match u8::try_from(value) {
+                    Ok(b'\n') => {
+                        let line = self.line.trim_end_matches('\r').to_string();
+                        outputs.push(AsciiOutput::Line(line));
+                        self.line.clear();
+                    }
+                    Ok(code) if code.is_ascii() => self.line.push(code as char),
+                    _ => {
+                        self.flush(&mut outputs);
+                        outputs.push(AsciiOutput::Value(value));
+                    }
+                },
+                StepResult::NeedsInput => break,
+                StepResult::Halted => {
+                    self.halted = true;
+                    break;
+                }
+            }
+        }
+        self.flush(&mut outputs);
+
+        Ok(outputs)
+    }
+
+    // Sends a line and runs until the program wants the next one
+    pub fn execute(&mut self, line: &str) -> Result<Vec<AsciiOutput>, IntcodeError> {
+        self.send_line(line)?;
+        self.run()
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn computer(&self) -> &Computer<VecDeque<i64>, Vec<i64>, M> {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut Computer<VecDeque<i64>, Vec<i64>, M> {
+        &mut self.computer
+    }
+
+    fn flush(&mut self, outputs: &mut Vec<AsciiOutput>) {
+        if !self.line.is_empty() {
+            outputs.push(AsciiOutput::Line(self.line.clone()));
+            self.line.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    // Echoes what it reads, and outputs 1000 when it reads a full stop
+    fn echo() -> Ascii {
+        let program = assemble(
+            "
+            loop:   in   c
+                    eq   c #46 stop
+                    jt   stop #answer
+                    out  c
+                    jt   #1 #loop
+            answer: out  #1000
+                    jt   #1 #loop
+            c:      data 0
+            stop:   data 0
+            ",
+        )
+        .unwrap();
+        Ascii::new(&program)
+    }
+
+    fn line(text: &str) -> AsciiOutput {
+        AsciiOutput::Line(text.to_string())
+    }
+
+    #[test]
+    fn lines_and_values() {
+        let mut ascii = echo();
+        assert_eq!(
+            ascii.execute("one\r\ntwo."),
+            Ok(vec![
+                line("one"),
+                line("two"),
+                AsciiOutput::Value(1000),
+                line(""),
+            ])
+        );
+
+        // A prompt without its newline comes back as it is
+        ascii.send("name? ").unwrap();
+        assert_eq!(ascii.run(), Ok(vec![line("name? ")]));
+        assert!(!ascii.halted());
+    }
+
+    #[test]
+    fn output_beyond_ascii() {
+        let program = assemble(
+            "
+                    out  #104
+                    out  #13
+                    out  #10
+                    out  #128
+                    out  #104
+                    out  #233
+                    out  #-1
+                    hlt
+            ",
+        )
+        .unwrap();
+        let mut ascii = Ascii::new(&program);
+        assert_eq!(
+            ascii.run(),
+            Ok(vec![
+                line("h"),
+                AsciiOutput::Value(128),
+                line("h"),
+                AsciiOutput::Value(233),
+                AsciiOutput::Value(-1),
+            ])
+        );
+        assert!(ascii.halted());
+    }
+
+    #[test]
+    fn input_beyond_ascii() {
+        let mut ascii = echo();
+        assert_eq!(
+            ascii.execute("héllo"),
+            Err(IntcodeError::NonAsciiInput { character: 'é' })
+        );
+        assert!(ascii.computer().input().is_empty());
+        assert_eq!(
+            IntcodeError::NonAsciiInput { character: 'é' }.to_string(),
+            "Cannot send 'é' (U+00E9) to an ASCII program"
+        );
+    }
+}
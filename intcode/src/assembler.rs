@@ -0,0 +1,373 @@
+// A small assembly language for Intcode, using the disassembler's
+// mnemonics:
+//
+//     ; Counts down from 5, printing each value
+//     loop:   out  count
+//             add  count #-1 count
+//             jt   count #loop
+//             hlt
+//     count:  data 5
+//
+// Operands are position mode by default, '#' marks immediate mode and '@'
+// marks relative mode. An operand is an integer, a label, or a label plus
+// or minus an integer. A label stands for the address of the instruction
+// or data which follows it. "data" emits its operands as they are, and
+// comments run from ';' to the end of the line.
+
+use std::collections::HashMap;
+
+use crate::disassembler::{disassemble, Line, Operand};
+use crate::error::AssembleError;
+use crate::instruction::{Mode, Opcode};
+
+enum Value {
+    Integer(i64),
+    Label(String, i64),
+}
+
+enum Item {
+    Instruction(Opcode, Vec<(Mode, Value)>),
+    Data(Vec<Value>),
+}
+
+impl Item {
+    fn len(&self) -> usize {
+        match self {
+            Item::Instruction(_, operands) => 1 + operands.len(),
+            Item::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_value(line: usize, text: &str) -> Result<Value, AssembleError> {
+    let invalid = || AssembleError::InvalidOperand {
+        line,
+        operand: text.to_string(),
+    };
+
+    if let Ok(integer) = text.parse::<i64>() {
+        return Ok(Value::Integer(integer));
+    }
+
+    let (label, adjustment) = match text.find(['+', '-']) {
+        Some(index) => {
+            let adjustment = text[index..]
+                .trim_start_matches('+')
+                .parse::<i64>()
+                .map_err(|_| invalid())?;
+            (&text[..index], adjustment)
+        }
+        None => (text, 0),
+    };
+
+    if is_label(label) {
+        Ok(Value::Label(label.to_string(), adjustment))
+    } else {
+        Err(invalid())
+    }
+}
+
+fn parse_operand(line: usize, text: &str) -> Result<(Mode, Value), AssembleError> {
+    if let Some(rest) = text.strip_prefix('#') {
+        Ok((Mode::Immediate, parse_value(line, rest)?))
+    } else if let Some(rest) = text.strip_prefix('@') {
+        Ok((Mode::Relative, parse_value(line, rest)?))
+    } else {
+        Ok((Mode::Position, parse_value(line, text)?))
+    }
+}
+
+fn resolve(
+    line: usize,
+    value: &Value,
+    labels: &HashMap<String, usize>,
+) -> Result<i64, AssembleError> {
+    match value {
+        Value::Integer(integer) => Ok(*integer),
+        Value::Label(label, adjustment) => match labels.get(label) {
+            Some(&address) => Ok(address as i64 + adjustment),
+            None => Err(AssembleError::UndefinedLabel {
+                line,
+                label: label.clone(),
+            }),
+        },
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssembleError> {
+    let mut items = Vec::new();
+    let mut labels = HashMap::new();
+    let mut address = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let text = match text.find(';') {
+            Some(index) => &text[..index],
+            None => text,
+        };
+        let mut tokens = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .peekable();
+
+        while let Some(label) = tokens.peek().and_then(|token| token.strip_suffix(':')) {
+            if !is_label(label) {
+                return Err(AssembleError::InvalidLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(AssembleError::DuplicateLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+            tokens.next();
+        }
+
+        let mnemonic = match tokens.next() {
+            Some(mnemonic) => mnemonic.to_lowercase(),
+            None => continue,
+        };
+        let operands: Vec<&str> = tokens.collect();
+
+        let item = if mnemonic == "data" {
+            let values = operands
+                .iter()
+                .map(|operand| parse_value(line, operand))
+                .collect::<Result<Vec<Value>, AssembleError>>()?;
+            Item::Data(values)
+        } else {
+            let opcode = match Opcode::from_mnemonic(&mnemonic) {
+                Some(opcode) => opcode,
+                None => return Err(AssembleError::UnknownMnemonic { line, mnemonic }),
+            };
+            if operands.len() != opcode.parameter_count() {
+                return Err(AssembleError::OperandCount {
+                    line,
+                    mnemonic,
+                    expected: opcode.parameter_count(),
+                    found: operands.len(),
+                });
+            }
+            let operands = operands
+                .iter()
+                .map(|operand| parse_operand(line, operand))
+                .collect::<Result<Vec<(Mode, Value)>, AssembleError>>()?;
+            Item::Instruction(opcode, operands)
+        };
+
+        address += item.len();
+        items.push((line, item));
+    }
+
+    let mut program = Vec::with_capacity(address);
+
+    for (line, item) in items {
+        match item {
+            Item::Instruction(opcode, operands) => {
+                let mut instruction = opcode.code();
+                let mut scale = 100;
+                for (mode, _) in operands.iter() {
+                    instruction += mode.digit() * scale;
+                    scale *= 10;
+                }
+                program.push(instruction);
+
+                for (_, value) in operands.iter() {
+                    program.push(resolve(line, value, &labels)?);
+                }
+            }
+            Item::Data(values) => {
+                for value in values.iter() {
+                    program.push(resolve(line, value, &labels)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+// Writes a program as assembly source which assembles back to the same
+// integers. Immediate jump targets which land on an instruction are given
+// labels.
+pub fn to_source(program: &[i64]) -> String {
+    let lines = disassemble(program);
+
+    let mut targets = HashMap::new();
+    for line in lines.iter() {
+        if let Line::Instruction {
+            opcode: Opcode::JumpIfTrue | Opcode::JumpIfFalse,
+            operands,
+            ..
+        } = line
+        {
+            if let Operand::Immediate(target) = operands[1] {
+                if lines.iter().any(|line| {
+                    matches!(line, Line::Instruction { .. }) && line.address() as i64 == target
+                }) {
+                    targets.insert(target, format!("L{}", target));
+                }
+            }
+        }
+    }
+
+    let mut result = String::new();
+
+    for line in lines.iter() {
+        if let Some(label) = targets.get(&(line.address() as i64)) {
+            result.push_str(&format!("{}:\n", label));
+        }
+
+        match line {
+            Line::Instruction {
+                opcode, operands, ..
+            } => {
+                let mut text = format!("    {:<4}", opcode.mnemonic());
+                for (offset, operand) in operands.iter().enumerate() {
+                    text.push(' ');
+                    text.push_str(&match (opcode, offset, operand) {
+                        (
+                            Opcode::JumpIfTrue | Opcode::JumpIfFalse,
+                            1,
+                            Operand::Immediate(target),
+                        ) if targets.contains_key(target) => {
+                            format!("#{}", targets[target])
+                        }
+                        (_, _, Operand::Position(integer)) => format!("{}", integer),
+                        (_, _, Operand::Immediate(integer)) => format!("#{}", integer),
+                        (_, _, Operand::Relative(integer)) => format!("@{}", integer),
+                    });
+                }
+                result.push_str(text.trim_end());
+            }
+            Line::Data { value, .. } => result.push_str(&format!("    data {}", value)),
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example programs from the puzzles
+    const EXAMPLES: [&[i64]; 6] = [
+        &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+        &[
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ],
+        &[
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ],
+        &[
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ],
+        &[1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+        &[104, 1125899906842624, 99],
+    ];
+
+    #[test]
+    fn examples_round_trip() {
+        for program in EXAMPLES {
+            let source = to_source(program);
+            assert_eq!(assemble(&source).as_deref(), Ok(program), "{}", source);
+        }
+    }
+
+    #[test]
+    fn odd_programs_round_trip() {
+        // Unknown opcodes, unsupported modes, truncated instructions and
+        // immediate mode writes
+        for program in [
+            &[] as &[i64],
+            &[42, 7, -3],
+            &[1301, 1, 2, 3, 99],
+            &[1, 0, 0],
+            &[11101, 1, 2, 3, 99],
+        ] {
+            let source = to_source(program);
+            assert_eq!(assemble(&source).as_deref(), Ok(program), "{}", source);
+        }
+    }
+
+    #[test]
+    fn labels() {
+        let source = "
+            ; Counts down from 5, printing each value
+            loop:   out  count
+                    add  count #-1 count
+                    jt   count #loop
+                    hlt
+            count:  data 5
+        ";
+
+        assert_eq!(
+            assemble(source),
+            Ok(vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 5])
+        );
+    }
+
+    #[test]
+    fn duplicate_label() {
+        assert_eq!(
+            assemble("a: hlt\na: hlt"),
+            Err(AssembleError::DuplicateLabel {
+                line: 2,
+                label: String::from("a")
+            })
+        );
+    }
+
+    #[test]
+    fn undefined_label() {
+        assert_eq!(
+            assemble("jt #1 #nowhere"),
+            Err(AssembleError::UndefinedLabel {
+                line: 1,
+                label: String::from("nowhere")
+            })
+        );
+    }
+
+    #[test]
+    fn wrong_operand_count() {
+        assert_eq!(
+            assemble("hlt\nadd 1 2"),
+            Err(AssembleError::OperandCount {
+                line: 2,
+                mnemonic: String::from("add"),
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_mnemonic() {
+        assert_eq!(
+            assemble("jmp #0"),
+            Err(AssembleError::UnknownMnemonic {
+                line: 1,
+                mnemonic: String::from("jmp")
+            })
+        );
+    }
+}
@@ -0,0 +1,55 @@
+// Runs an ASCII Intcode program, such as a text adventure, as a
+// conversation on the terminal:
+//
+//     cargo run --bin ascii -- <program file>
+//
+// Each line typed is sent to the program; a line which is not ASCII is
+// refused. Values outside the ASCII range are printed as numbers.
+
+use intcode::{parse_program, Ascii};
+use std::env;
+use std::fs;
+use std::io::{stdin, BufRead};
+use std::process;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: ascii <program file>");
+            process::exit(1);
+        }
+    };
+
+    let program = parse_program(&fs::read_to_string(&path).unwrap()).unwrap();
+
+    let mut ascii = Ascii::new(&program);
+    let stdin = stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        match ascii.run() {
+            Ok(outputs) => {
+                for output in outputs {
+                    println!("{}", output);
+                }
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                process::exit(1);
+            }
+        }
+        if ascii.halted() {
+            break;
+        }
+
+        match lines.next() {
+            Some(line) => {
+                if let Err(error) = ascii.send_line(&line.unwrap()) {
+                    eprintln!("error: {}", error);
+                }
+            }
+            None => break,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use intcode::assemble;
+use std::io::{stdin, Read};
+use std::process;
+
+fn main() {
+    let mut input = String::new();
+    stdin().read_to_string(&mut input).unwrap();
+
+    match assemble(&input) {
+        Ok(program) => {
+            let integers: Vec<String> = program.iter().map(|x| x.to_string()).collect();
+            println!("{}", integers.join(","));
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+// Prints the control flow graph of the program on stdin in Graphviz DOT
+// format, for example
+//
+//     cargo run --bin cfg < input.txt | dot -Tsvg > cfg.svg
+
+use intcode::{parse_program, ControlFlowGraph};
+use std::io::{stdin, Read};
+
+fn main() {
+    let mut input = String::new();
+    stdin().read_to_string(&mut input).unwrap();
+
+    let program = parse_program(&input).unwrap();
+
+    print!("{}", ControlFlowGraph::new(&program).to_dot());
+}
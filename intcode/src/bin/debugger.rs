@@ -0,0 +1,254 @@
+// An interactive debugger for Intcode programs:
+//
+//     cargo run --bin debugger -- <program file>
+//
+// Type "help" at the prompt for the list of commands.
+
+use intcode::{parse_program, Computer, IntcodeError, NoTrace, StepResult};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::env;
+use std::fs;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::process;
+
+const HELP: &str = "\
+step [n]            execute n instructions (default 1)
+back [n]            undo n instructions (default 1)
+continue            run until a breakpoint, watchpoint, input wait or halt
+break [ip]          toggle a breakpoint at ip, or list breakpoints
+watch [address]     toggle a watchpoint on address, or list watchpoints
+regs                print ip and rb
+stack               print the inferred call stack
+dump start [count]  print count memory cells from start (default 16)
+list [count]        disassemble count instructions from ip (default 8)
+input values...     queue input values
+quit                leave the debugger";
+
+enum Stop {
+    Breakpoint,
+    Watchpoint { address: usize, old: i64, new: i64 },
+    NeedsInput,
+    Halted,
+    Error(IntcodeError),
+}
+
+struct Debugger {
+    computer: Computer<VecDeque<i64>, Vec<i64>>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, i64>,
+}
+
+impl Debugger {
+    fn new(program: &[i64]) -> Debugger {
+        let mut computer = Computer::new(program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_history(true);
+        computer.set_code_tracking(true);
+
+        Debugger {
+            computer,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+        }
+    }
+
+    fn print_current(&self) {
+        println!(
+            "rb: {:>4} | {}",
+            self.computer.rb(),
+            self.computer.decode(self.computer.ip())
+        );
+    }
+
+    fn step(&mut self) -> Option<Stop> {
+        match self.computer.step() {
+            Ok(None) => (),
+            Ok(Some(StepResult::Output(value))) => println!("output: {}", value),
+            Ok(Some(StepResult::NeedsInput)) => return Some(Stop::NeedsInput),
+            Ok(Some(StepResult::Halted)) => return Some(Stop::Halted),
+            Err(error) => return Some(Stop::Error(error)),
+        }
+
+        for write in self.computer.take_code_writes() {
+            println!(
+                "code modified by ip {} at address {}: {} => {}",
+                write.ip, write.address, write.old, write.new
+            );
+        }
+
+        for (&address, old) in self.watchpoints.iter_mut() {
+            let new = self.computer.get_value(address);
+            if new != *old {
+                let old_value = *old;
+                *old = new;
+                return Some(Stop::Watchpoint {
+                    address,
+                    old: old_value,
+                    new,
+                });
+            }
+        }
+
+        if self.breakpoints.contains(&self.computer.ip()) {
+            return Some(Stop::Breakpoint);
+        }
+
+        None
+    }
+
+    fn report(&self, stop: Stop) {
+        match stop {
+            Stop::Breakpoint => println!("breakpoint at ip {}", self.computer.ip()),
+            Stop::Watchpoint { address, old, new } => {
+                println!("watchpoint at address {}: {} => {}", address, old, new)
+            }
+            Stop::NeedsInput => println!("waiting for input at ip {}", self.computer.ip()),
+            Stop::Halted => println!("halted at ip {}", self.computer.ip()),
+            Stop::Error(error) => {
+                // An error inside a subroutine shows the call stack
+                println!("error: {}", error);
+            }
+        }
+    }
+
+    fn command(&mut self, words: &[&str]) -> Result<(), String> {
+        let number = |index: usize, default: Option<usize>| -> Result<usize, String> {
+            match (words.get(index), default) {
+                (Some(word), _) => word
+                    .parse::<usize>()
+                    .map_err(|_| format!("expected a number, not {}", word)),
+                (None, Some(default)) => Ok(default),
+                (None, None) => Err(String::from("expected a number")),
+            }
+        };
+
+        match words[0] {
+            "s" | "step" => {
+                for _ in 0..number(1, Some(1))? {
+                    self.print_current();
+                    if let Some(stop) = self.step() {
+                        self.report(stop);
+                        break;
+                    }
+                }
+            }
+            "back" => {
+                for _ in 0..number(1, Some(1))? {
+                    if !self.computer.step_back() {
+                        println!("at the start of the history");
+                        break;
+                    }
+                }
+                for (&address, old) in self.watchpoints.iter_mut() {
+                    *old = self.computer.get_value(address);
+                }
+                self.print_current();
+            }
+            "c" | "continue" => loop {
+                if let Some(stop) = self.step() {
+                    self.report(stop);
+                    self.print_current();
+                    break;
+                }
+            },
+            "b" | "break" => {
+                if words.len() > 1 {
+                    let ip = number(1, None)?;
+                    if !self.breakpoints.remove(&ip) {
+                        self.breakpoints.insert(ip);
+                    }
+                }
+                println!("breakpoints: {:?}", self.breakpoints);
+            }
+            "w" | "watch" => {
+                if words.len() > 1 {
+                    let address = number(1, None)?;
+                    if self.watchpoints.remove(&address).is_none() {
+                        self.watchpoints
+                            .insert(address, self.computer.get_value(address));
+                    }
+                }
+                println!(
+                    "watchpoints: {:?}",
+                    self.watchpoints.keys().collect::<Vec<&usize>>()
+                );
+            }
+            "r" | "regs" => println!(
+                "ip: {} rb: {} instructions: {}",
+                self.computer.ip(),
+                self.computer.rb(),
+                self.computer.instructions()
+            ),
+            "stack" => println!("{}", self.computer.backtrace()),
+            "d" | "dump" => {
+                let start = number(1, None)?;
+                let count = number(2, Some(16))?;
+                for row in (start..start + count).step_by(8) {
+                    let cells: Vec<String> = (row..(row + 8).min(start + count))
+                        .map(|address| format!("{:>6}", self.computer.get_value(address)))
+                        .collect();
+                    println!("{:>5}: {}", row, cells.join(" "));
+                }
+            }
+            "l" | "list" => {
+                let mut address = self.computer.ip();
+                for _ in 0..number(1, Some(8))? {
+                    let line = self.computer.decode(address);
+                    println!("{}", line);
+                    address += line.len();
+                }
+            }
+            "i" | "input" => {
+                for word in words[1..].iter() {
+                    let value = word
+                        .parse::<i64>()
+                        .map_err(|_| format!("expected an integer, not {}", word))?;
+                    self.computer.input_mut().push_back(value);
+                }
+                println!("pending input: {:?}", self.computer.input());
+            }
+            "h" | "help" => println!("{}", HELP),
+            _ => return Err(format!("unknown command {}; try help", words[0])),
+        }
+
+        Ok(())
+    }
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: debugger <program file>");
+            process::exit(1);
+        }
+    };
+
+    let program = parse_program(&fs::read_to_string(&path).unwrap()).unwrap();
+
+    let mut debugger = Debugger::new(&program);
+    debugger.print_current();
+
+    let stdin = stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("(intcode) ");
+        stdout().flush().unwrap();
+
+        let line = match lines.next() {
+            Some(line) => line.unwrap(),
+            None => break,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.first() {
+            None => continue,
+            Some(&"q") | Some(&"quit") => break,
+            Some(_) => {
+                if let Err(message) = debugger.command(&words) {
+                    println!("{}", message);
+                }
+            }
+        }
+    }
+}
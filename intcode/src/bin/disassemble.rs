@@ -0,0 +1,19 @@
+// Prints a listing of the program on stdin, or with --source, assembly
+// source which the assemble binary turns back into the same program.
+
+use intcode::{listing, parse_program, to_source};
+use std::env;
+use std::io::{stdin, Read};
+
+fn main() {
+    let mut input = String::new();
+    stdin().read_to_string(&mut input).unwrap();
+
+    let program = parse_program(&input).unwrap();
+
+    if env::args().any(|arg| arg == "--source") {
+        print!("{}", to_source(&program));
+    } else {
+        print!("{}", listing(&program));
+    }
+}
@@ -0,0 +1,14 @@
+// Prints a Rust module which runs the program on stdin, to be saved as
+// a module file in a crate which depends on intcode.
+
+use intcode::{parse_program, transpile};
+use std::io::{stdin, Read};
+
+fn main() {
+    let mut input = String::new();
+    stdin().read_to_string(&mut input).unwrap();
+
+    let program = parse_program(&input).unwrap();
+
+    print!("{}", transpile(&program));
+}
@@ -0,0 +1,93 @@
+use crate::instruction::{Mode, Opcode};
+use crate::table::IndexTable;
+
+// The longest instruction: an opcode and three parameters
+const MAX_LENGTH: usize = 4;
+
+// An instruction as fetched from memory, before its parameters are
+// resolved against the relative base and memory
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Decoded {
+    pub(crate) instruction: i64,
+    pub(crate) opcode: Opcode,
+    pub(crate) modes: [Mode; 3],
+    pub(crate) integers: [i64; 3],
+}
+
+impl Decoded {
+    fn len(&self) -> usize {
+        1 + self.opcode.parameter_count()
+    }
+}
+
+// Instructions at ips below this are found through a table rather than a
+// HashMap
+const DENSE_IPS: usize = 1 << 16;
+
+// Decoded instructions keyed by the ip they were fetched at. A write to
+// any cell an entry was decoded from drops that entry.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct DecodeCache {
+    entries: IndexTable<Decoded, DENSE_IPS>,
+}
+
+impl DecodeCache {
+    #[inline]
+    pub(crate) fn get(&self, ip: usize) -> Option<Decoded> {
+        self.entries.get(ip).copied()
+    }
+
+    pub(crate) fn insert(&mut self, ip: usize, decoded: Decoded) {
+        self.entries.insert(ip, decoded);
+    }
+
+    pub(crate) fn invalidate(&mut self, address: usize) {
+        for ip in address.saturating_sub(MAX_LENGTH - 1)..=address {
+            let covers = |decoded: &Decoded| ip + decoded.len() > address;
+            if self.entries.get(ip).is_some_and(covers) {
+                self.entries.remove(ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::computer::{Computer, StepResult};
+    use crate::trace::NoTrace;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn far_jump() {
+        // Writes a halt a long way off and jumps to it
+        let program = [1101, 99, 0, 1_000_000_000, 1105, 1, 1_000_000_000];
+        for cached in [false, true] {
+            let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+            computer.set_decode_cache(cached);
+            computer.run().unwrap();
+        }
+
+        const FAR: usize = 1_000_000_000;
+        let program = assemble(&format!(
+            "
+            add  #104 #0 {}
+            add  #7 #0 {}
+            add  #99 #0 {}
+            jt   #1 #{}
+            ",
+            FAR,
+            FAR + 1,
+            FAR + 2,
+            FAR
+        ))
+        .unwrap();
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(7)));
+
+        // A write to a distant instruction which has been cached drops it
+        computer.set_value(FAR + 1, 8);
+        computer.set_ip(FAR);
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(8)));
+    }
+}
@@ -0,0 +1,382 @@
+// Static control flow analysis of a program. Instructions are decoded from
+// address 0 and from every jump target, call entry and return address found
+// on the way, so that data between instructions is left out and code after
+// it is still found. The targets of jumps read from memory are not known,
+// so code reached only that way is left out too.
+//
+// Subroutine calls are recognised from the calling convention the AoC
+// programs use: the caller stores the return address in a relative mode
+// cell, then jumps unconditionally to the subroutine, for example
+//
+//     add  #25 #0 @1    ; [rb+1] = 25
+//     jt   #1 #1234     ; call 1234, returning to 25
+//
+// and the subroutine later returns by jumping to the address in that cell.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::disassembler::{decode, Line, Operand};
+use crate::instruction::Opcode;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    Call,
+    // From a call site to the instruction the call returns to
+    CallReturn,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Block {
+    pub start: usize,
+    pub lines: Vec<Line>,
+    // Whether the block ends in a jump whose target is read from memory
+    pub indirect: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<Block>,
+    pub edges: Vec<Edge>,
+    pub subroutines: BTreeSet<usize>,
+}
+
+// The target of a jump with an immediate target, and whether the jump is
+// always taken, never taken, or depends on its condition
+enum Jump {
+    Always(usize),
+    Never,
+    Conditional(usize),
+    Indirect { always: bool },
+}
+
+fn jump(line: &Line) -> Option<Jump> {
+    let (opcode, operands) = match line {
+        Line::Instruction {
+            opcode: opcode @ (Opcode::JumpIfTrue | Opcode::JumpIfFalse),
+            operands,
+            ..
+        } => (opcode, operands),
+        _ => return None,
+    };
+
+    let taken = match operands[0] {
+        Operand::Immediate(condition) => Some((condition != 0) == (*opcode == Opcode::JumpIfTrue)),
+        _ => None,
+    };
+
+    Some(match (taken, operands[1]) {
+        (Some(false), _) => Jump::Never,
+        (_, Operand::Immediate(target)) if target < 0 => Jump::Never,
+        (Some(true), Operand::Immediate(target)) => Jump::Always(target as usize),
+        (None, Operand::Immediate(target)) => Jump::Conditional(target as usize),
+        (taken, _) => Jump::Indirect {
+            always: taken.is_some(),
+        },
+    })
+}
+
+// The value an add or multiply of two immediates stores in a relative mode
+// cell, if that is what the line does
+fn stored_constant(line: &Line) -> Option<i64> {
+    match line {
+        Line::Instruction {
+            opcode, operands, ..
+        } => match (opcode, &operands[..]) {
+            (Opcode::Add, [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)]) => {
+                a.checked_add(*b)
+            }
+            (
+                Opcode::Multiply,
+                [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)],
+            ) => a.checked_mul(*b),
+            _ => None,
+        },
+        Line::Data { .. } => None,
+    }
+}
+
+// Whether nothing can follow the line in the same block: a jump, a halt,
+// or a cell which does not hold an instruction
+fn ends_block(line: &Line) -> bool {
+    match line {
+        Line::Instruction {
+            opcode: Opcode::Halt,
+            ..
+        }
+        | Line::Data { .. } => true,
+        Line::Instruction { .. } => jump(line).is_some(),
+    }
+}
+
+impl ControlFlowGraph {
+    pub fn new(program: &[i64]) -> ControlFlowGraph {
+        // Every line reached from address 0, keyed by address. A reached
+        // cell which does not hold an instruction is kept as data, so that
+        // the graph shows where the program would fail.
+        let mut lines: BTreeMap<usize, Line> = BTreeMap::new();
+        // Calls, keyed by the address of the jump, with the subroutine and
+        // return address
+        let mut calls = BTreeMap::new();
+        let mut leaders: BTreeSet<usize> = BTreeSet::from([0]);
+        let mut pending = vec![0];
+
+        while let Some(address) = pending.pop() {
+            if lines.contains_key(&address) {
+                continue;
+            }
+            let line = match decode(program, address) {
+                Some(line) => line,
+                None => match program.get(address) {
+                    Some(&value) => Line::Data { address, value },
+                    None => continue,
+                },
+            };
+
+            // Where the program may go from here: on to the next instruction
+            // in the same block, or to addresses which start blocks
+            let next = address + line.len();
+            let mut fallthrough = None;
+            let mut targets = Vec::new();
+            match jump(&line) {
+                Some(Jump::Always(target)) => {
+                    // A call stores its return address just before jumping
+                    let stored = address
+                        .checked_sub(4)
+                        .and_then(|start| decode(program, start))
+                        .and_then(|previous| stored_constant(&previous));
+                    if stored == Some(next as i64) {
+                        calls.insert(address, (target, next));
+                        targets.push(next);
+                    }
+                    targets.push(target);
+                }
+                Some(Jump::Conditional(target)) => targets.extend([target, next]),
+                Some(Jump::Never) | Some(Jump::Indirect { always: false }) => targets.push(next),
+                Some(Jump::Indirect { always: true }) => (),
+                None if !ends_block(&line) => fallthrough = Some(next),
+                None => (),
+            }
+
+            leaders.extend(targets.iter().copied());
+            pending.extend(targets);
+            pending.extend(fallthrough);
+            lines.insert(address, line);
+        }
+
+        let mut blocks: Vec<Block> = Vec::new();
+        for &start in leaders.iter() {
+            let mut address = start;
+            let mut block = Block {
+                start,
+                lines: Vec::new(),
+                indirect: false,
+            };
+            while let Some(line) = lines.get(&address) {
+                if address != start && leaders.contains(&address) {
+                    break;
+                }
+                block.lines.push(line.clone());
+                address += line.len();
+                if ends_block(line) {
+                    break;
+                }
+            }
+            if !block.lines.is_empty() {
+                blocks.push(block);
+            }
+        }
+
+        let block_starts: BTreeSet<usize> = blocks.iter().map(|block| block.start).collect();
+        let mut edges = Vec::new();
+        for block in blocks.iter_mut() {
+            let last = block.lines.last().unwrap();
+            let next = last.address() + last.len();
+            let mut edge = |to: usize, kind: EdgeKind| {
+                if block_starts.contains(&to) {
+                    edges.push(Edge {
+                        from: block.start,
+                        to,
+                        kind,
+                    });
+                }
+            };
+
+            match jump(last) {
+                Some(Jump::Always(target)) => match calls.get(&last.address()) {
+                    Some(&(subroutine, return_address)) => {
+                        edge(subroutine, EdgeKind::Call);
+                        edge(return_address, EdgeKind::CallReturn);
+                    }
+                    None => edge(target, EdgeKind::Jump),
+                },
+                Some(Jump::Conditional(target)) => {
+                    edge(target, EdgeKind::Jump);
+                    edge(next, EdgeKind::Fallthrough);
+                }
+                Some(Jump::Never) => edge(next, EdgeKind::Fallthrough),
+                Some(Jump::Indirect { always }) => {
+                    block.indirect = true;
+                    if !always {
+                        edge(next, EdgeKind::Fallthrough);
+                    }
+                }
+                None if !ends_block(last) => edge(next, EdgeKind::Fallthrough),
+                None => (),
+            }
+        }
+
+        let subroutines = calls.values().map(|&(target, _)| target).collect();
+
+        ControlFlowGraph {
+            blocks,
+            edges,
+            subroutines,
+        }
+    }
+
+    // Writes the graph in Graphviz DOT format. Subroutine entries are drawn
+    // with a double border, calls as dashed edges and call returns as
+    // dotted ones.
+    pub fn to_dot(&self) -> String {
+        let mut result = String::from("digraph intcode {\n");
+        result.push_str("    node [shape=box fontname=monospace];\n");
+
+        for block in self.blocks.iter() {
+            let mut label = String::new();
+            for line in block.lines.iter() {
+                label.push_str(&format!("{}\\l", line));
+            }
+            if block.indirect {
+                label.push_str("(indirect jump)\\l");
+            }
+            let style = if self.subroutines.contains(&block.start) {
+                " peripheries=2"
+            } else {
+                ""
+            };
+            result.push_str(&format!(
+                "    b{} [label=\"{}\"{}];\n",
+                block.start, label, style
+            ));
+        }
+
+        for edge in self.edges.iter() {
+            let style = match edge.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Jump => " [color=blue]",
+                EdgeKind::Call => " [style=dashed label=\"call\"]",
+                EdgeKind::CallReturn => " [style=dotted]",
+            };
+            result.push_str(&format!("    b{} -> b{}{};\n", edge.from, edge.to, style));
+        }
+
+        result.push_str("}\n");
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    fn starts(graph: &ControlFlowGraph) -> Vec<(usize, usize)> {
+        graph
+            .blocks
+            .iter()
+            .map(|block| (block.start, block.lines.len()))
+            .collect()
+    }
+
+    fn edges(graph: &ControlFlowGraph) -> Vec<(usize, usize, EdgeKind)> {
+        graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.kind))
+            .collect()
+    }
+
+    #[test]
+    fn jumps_over_data() {
+        let graph = ControlFlowGraph::new(&[1105, 1, 4, 1, 104, 1, 99]);
+        assert_eq!(starts(&graph), vec![(0, 1), (4, 2)]);
+        assert_eq!(edges(&graph), vec![(0, 4, EdgeKind::Jump)]);
+        assert_eq!(
+            graph.to_dot(),
+            "digraph intcode {\n    \
+             node [shape=box fontname=monospace];\n    \
+             b0 [label=\"    0: jt   [I|1] [I|4]\\l\"];\n    \
+             b4 [label=\"    4: out  [I|1]\\l    6: hlt\\l\"];\n    \
+             b0 -> b4 [color=blue];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        let program = assemble(
+            "
+                    arb  #stack
+                    in   x
+                    add  #back #0 @0
+                    jt   #1 #double
+            back:   jf   x #zero
+                    out  x
+            zero:   hlt
+            double: mul  x #2 x
+                    jt   #1 @0
+            x:      data 0
+            stack:  data 0
+            ",
+        )
+        .unwrap();
+        let graph = ControlFlowGraph::new(&program);
+
+        assert_eq!(
+            starts(&graph),
+            vec![(0, 4), (11, 1), (14, 1), (16, 1), (17, 2)]
+        );
+        assert_eq!(
+            edges(&graph),
+            vec![
+                (0, 17, EdgeKind::Call),
+                (0, 11, EdgeKind::CallReturn),
+                (11, 16, EdgeKind::Jump),
+                (11, 14, EdgeKind::Fallthrough),
+                (14, 16, EdgeKind::Fallthrough),
+            ]
+        );
+        assert_eq!(graph.subroutines, BTreeSet::from([17]));
+        assert!(graph.blocks[4].indirect);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("    b17 [label=\"   17: mul  [P|24] [I|2] [P|24]\\l   21: jt   [I|1] [R|0]\\l(indirect jump)\\l\" peripheries=2];\n"));
+        assert!(dot.contains("    b0 -> b17 [style=dashed label=\"call\"];\n"));
+        assert!(dot.contains("    b0 -> b11 [style=dotted];\n"));
+        assert!(dot.contains("    b14 -> b16;\n"));
+    }
+
+    #[test]
+    fn reached_cells_which_are_not_instructions() {
+        // The jump lands on an unknown opcode, which stops the program
+        let graph = ControlFlowGraph::new(&[1105, 1, 3, 98]);
+        assert_eq!(starts(&graph), vec![(0, 1), (3, 1)]);
+        assert_eq!(
+            graph.blocks[1].lines,
+            vec![Line::Data {
+                address: 3,
+                value: 98
+            }]
+        );
+        assert_eq!(edges(&graph), vec![(0, 3, EdgeKind::Jump)]);
+    }
+}
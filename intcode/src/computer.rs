@@ -0,0 +1,1401 @@
+use std::time::Duration;
+
+use crate::cache::{DecodeCache, Decoded};
+use crate::disassembler::{decode_with, Line};
+use crate::error::IntcodeError;
+use crate::instruction::{Mode, Opcode, Parameter};
+use crate::io::{Input, Output, Timeout};
+use crate::memory::{Memory, PagedMemory};
+use crate::modification::{CodeTracker, CodeWrite};
+use crate::profile::Profile;
+use crate::snapshot::Snapshot;
+use crate::stack::{CallStack, Frame, StackChange};
+use crate::trace::{MemoryWrite, NoTrace, TraceEvent, TraceSink};
+use crate::transcript::{Replay, Transcript, TranscriptEvent};
+
+// Bounds on a Computer's execution. A machine which reaches one stops with
+// the matching IntcodeError: InstructionLimit, AddressLimit or InputTimeout.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Limits {
+    pub max_instructions: Option<u64>,
+    pub max_address: Option<usize>,
+    pub input_timeout: Option<Duration>,
+}
+
+// How add and multiply deal with results beyond the range of a cell.
+// Wrapping keeps the low 64 bits, as a release build always did. Checked
+// stops the machine with IntcodeError::Overflow. Widened lets cells hold
+// i128 values (see Memory::get_wide): add, multiply, comparisons and jump
+// conditions work on whole values, and only results beyond an i128 stop
+// the machine with Overflow. A value beyond an i64 stops it with WideValue
+// where an i64 is needed: in an instruction's own cells, or as an output,
+// jump target or relative base adjustment. Traces and code writes show the
+// low 64 bits of such values.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Arithmetic {
+    #[default]
+    Wrapping,
+    Checked,
+    Widened,
+}
+
+impl Arithmetic {
+    // Returns None if the sum cannot be stored. Values beyond the range of
+    // an i64 only occur under Widened.
+    pub fn add(&self, left: i128, right: i128) -> Option<i128> {
+        match self {
+            Arithmetic::Wrapping => Some((left as i64).wrapping_add(right as i64).into()),
+            Arithmetic::Checked => (left as i64).checked_add(right as i64).map(i128::from),
+            Arithmetic::Widened => left.checked_add(right),
+        }
+    }
+
+    // Returns None if the product cannot be stored
+    pub fn multiply(&self, left: i128, right: i128) -> Option<i128> {
+        match self {
+            Arithmetic::Wrapping => Some((left as i64).wrapping_mul(right as i64).into()),
+            Arithmetic::Checked => (left as i64).checked_mul(right as i64).map(i128::from),
+            Arithmetic::Widened => left.checked_mul(right),
+        }
+    }
+}
+
+// What one executed instruction changed, so that it can be undone
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Undo {
+    ip: usize,
+    rb: i64,
+    write: Option<(usize, i128)>,
+    len: usize,
+    input: Option<i64>,
+    replayed: bool,
+    stack: Option<StackChange>,
+    stored: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StepResult {
+    NeedsInput,
+    Output(i64),
+    Halted,
+}
+
+pub struct Computer<I, O, M = PagedMemory> {
+    memory: M,
+    ip: usize,
+    rb: i64,
+    input: I,
+    output: O,
+    trace: Box<dyn TraceSink + Send>,
+    tracing: bool,
+    profile: Option<Box<Profile>>,
+    code: Option<Box<CodeTracker>>,
+    instructions: u64,
+    limits: Limits,
+    arithmetic: Arithmetic,
+    history: Option<Vec<Undo>>,
+    transcript: Option<Box<Transcript>>,
+    replay: Option<Box<Replay>>,
+    unread: Vec<i64>,
+    stack: CallStack,
+    cache: Option<DecodeCache>,
+}
+
+impl<I: Input, O: Output> Computer<I, O> {
+    pub fn new<T: TraceSink + Send + 'static>(
+        program: &[i64],
+        input: I,
+        output: O,
+        trace: T,
+    ) -> Computer<I, O> {
+        Computer::with_memory(PagedMemory::from_program(program), input, output, trace)
+    }
+}
+
+impl<I: Input, O: Output, M: Memory> Computer<I, O, M> {
+    pub fn with_memory<T: TraceSink + Send + 'static>(
+        memory: M,
+        input: I,
+        output: O,
+        trace: T,
+    ) -> Computer<I, O, M> {
+        Computer {
+            memory,
+            ip: 0,
+            rb: 0,
+            input,
+            output,
+            tracing: trace.enabled(),
+            trace: Box::new(trace),
+            profile: None,
+            code: None,
+            instructions: 0,
+            limits: Limits::default(),
+            arithmetic: Arithmetic::default(),
+            history: None,
+            transcript: None,
+            replay: None,
+            unread: Vec::new(),
+            stack: CallStack::default(),
+            cache: Some(DecodeCache::default()),
+        }
+    }
+
+    // Resumes from a snapshot. The snapshot's pending input is put back
+    // into the input source ahead of anything already in it, or read before
+    // it where the source cannot hold values, and its pending output is put
+    // back into the output sink where the sink can hold it.
+    pub fn from_snapshot<T: TraceSink + Send + 'static>(
+        snapshot: &Snapshot,
+        input: I,
+        output: O,
+        trace: T,
+    ) -> Computer<I, O, M> {
+        let mut memory = M::from_program(&[]);
+        for (address, run) in &snapshot.memory {
+            for (offset, &value) in run.iter().enumerate() {
+                memory.set(address + offset, value);
+            }
+        }
+        for &(address, value) in &snapshot.wide {
+            memory.set_wide(address, value);
+        }
+
+        let mut computer = Computer::with_memory(memory, input, output, trace);
+        computer.ip = snapshot.ip;
+        computer.rb = snapshot.rb;
+        computer.instructions = snapshot.instructions;
+        if !computer.input.restore(&snapshot.input) {
+            computer.unread = snapshot.input.iter().rev().copied().collect();
+        }
+        computer.output.restore(&snapshot.output);
+
+        computer
+    }
+
+    // Duplicates the machine mid-run, including its input and output. With
+    // PagedMemory the two machines share memory pages until either writes
+    // to them. The copy is not traced; see set_trace.
+    pub fn fork(&self) -> Computer<I, O, M>
+    where
+        I: Clone,
+        O: Clone,
+        M: Clone,
+    {
+        self.fork_with(self.input.clone(), self.output.clone())
+    }
+
+    // Duplicates the machine mid-run, giving the copy its own input and
+    // output
+    pub fn fork_with<J: Input, P: Output>(&self, input: J, output: P) -> Computer<J, P, M>
+    where
+        M: Clone,
+    {
+        Computer {
+            memory: self.memory.clone(),
+            ip: self.ip,
+            rb: self.rb,
+            input,
+            output,
+            trace: Box::new(NoTrace),
+            tracing: false,
+            profile: None,
+            code: None,
+            instructions: self.instructions,
+            limits: self.limits,
+            arithmetic: self.arithmetic,
+            history: None,
+            transcript: None,
+            replay: None,
+            unread: self.unread.clone(),
+            stack: self.stack.clone(),
+            cache: self.cache.as_ref().map(|_| DecodeCache::default()),
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ip: self.ip,
+            rb: self.rb,
+            instructions: self.instructions,
+            input: self
+                .unread
+                .iter()
+                .rev()
+                .copied()
+                .chain(self.input.pending())
+                .collect(),
+            output: self.output.pending(),
+            memory: self.memory.runs(),
+            wide: self.memory.wide_cells(),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        loop {
+            match self.run_until_io()? {
+                StepResult::NeedsInput => {
+                    let error = IntcodeError::InputUnavailable {
+                        ip: self.ip,
+                        instruction: self.get_opcode()?,
+                    };
+                    return Err(self.in_subroutine(error));
+                }
+                StepResult::Output(value) => self.output.write(value),
+                StepResult::Halted => return Ok(()),
+            }
+        }
+    }
+
+    // Runs until the program needs an input value which the input source
+    // cannot supply, produces an output value, or halts. Output values are
+    // returned here rather than written to the output sink.
+    pub fn run_until_io(&mut self) -> Result<StepResult, IntcodeError> {
+        loop {
+            if let Some(step_result) = self.step()? {
+                return Ok(step_result);
+            }
+        }
+    }
+
+    pub fn set_trace<T: TraceSink + Send + 'static>(&mut self, trace: T) {
+        self.tracing = trace.enabled();
+        self.trace = Box::new(trace);
+    }
+
+    // With profiling on, the machine counts executions per opcode and
+    // address, and writes a report to stderr the first time the program
+    // halts. Turning it on again starts a fresh profile.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = if enabled { Some(Box::default()) } else { None };
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_deref()
+    }
+
+    // With code tracking on, the machine remembers which cells it has
+    // executed as instructions from here on, and records each write which
+    // lands on one of them. Turning it on again starts afresh.
+    pub fn set_code_tracking(&mut self, enabled: bool) {
+        self.code = if enabled { Some(Box::default()) } else { None };
+    }
+
+    // The writes to executed code recorded so far, oldest first; empty if
+    // code tracking is off
+    pub fn code_writes(&self) -> &[CodeWrite] {
+        match self.code.as_deref() {
+            Some(code) => code.writes(),
+            None => &[],
+        }
+    }
+
+    // Takes the writes to executed code recorded since the last call, so
+    // that a host can react to them as they happen
+    pub fn take_code_writes(&mut self) -> Vec<CodeWrite> {
+        match self.code.as_mut() {
+            Some(code) => code.take_writes(),
+            None => Vec::new(),
+        }
+    }
+
+    // With recording on, every value the machine reads and every value it
+    // outputs from here on is added to a transcript, which the host can
+    // save and later replay. Values read again after step_back are recorded
+    // again. Turning recording on again starts a fresh transcript.
+    pub fn set_recording(&mut self, enabled: bool) {
+        self.transcript = if enabled { Some(Box::default()) } else { None };
+    }
+
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.transcript.as_deref()
+    }
+
+    // Takes the transcript recorded so far, leaving recording on with an
+    // empty one; empty if recording is off
+    pub fn take_transcript(&mut self) -> Transcript {
+        match self.transcript.as_mut() {
+            Some(transcript) => std::mem::take(transcript),
+            None => Transcript::default(),
+        }
+    }
+
+    // Replays a transcript from here on: the machine reads the recorded
+    // inputs in place of its input source, and stops with ReplayMismatch if
+    // it reads when the transcript has an output next, or outputs anything
+    // other than the next recorded output. Inputs undone by step_back are
+    // still read first, and undoing a replayed input or output steps the
+    // replay back too. Once the transcript runs out the machine goes back
+    // to its input source and outputs go unchecked.
+    pub fn replay(&mut self, transcript: Transcript) {
+        self.replay = Some(Box::new(Replay::new(transcript)));
+    }
+
+    // The events left to replay; zero once a replay has run its course, or
+    // if there is none
+    pub fn replay_remaining(&self) -> usize {
+        self.replay.as_ref().map_or(0, |replay| replay.remaining())
+    }
+
+    // With history on, each instruction executed from here on records what
+    // it changed, so that step_back and rewind_to can undo it. Inputs which
+    // are undone are read again before anything new from the input source;
+    // outputs cannot be taken back, and are produced again on the way
+    // forward. Turning history off discards it.
+    pub fn set_history(&mut self, enabled: bool) {
+        self.history = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    // Undoes the last instruction executed. Returns false if there is no
+    // history left to undo.
+    pub fn step_back(&mut self) -> bool {
+        let undo = match self.history.as_mut().and_then(|history| history.pop()) {
+            Some(undo) => undo,
+            None => return false,
+        };
+
+        if let Some((address, old)) = undo.write {
+            self.set_wide_value(address, old);
+        }
+        if self.memory.len() > undo.len {
+            self.memory.truncate(undo.len);
+            // Instructions which ran past the old end now lack parameters
+            if let Some(cache) = self.cache.as_mut() {
+                cache.invalidate(undo.len);
+            }
+        }
+        // A replayed input or output is given back to the replay, and an
+        // input from the input source is read again
+        if undo.replayed {
+            if let Some(replay) = self.replay.as_mut() {
+                replay.rewind();
+            }
+        } else if let Some(value) = undo.input {
+            self.unread.push(value);
+        }
+        self.stack.undo(undo.stack, undo.stored);
+        self.ip = undo.ip;
+        self.rb = undo.rb;
+        self.instructions -= 1;
+        if let Some(code) = self.code.as_mut() {
+            code.undo(self.instructions);
+        }
+
+        true
+    }
+
+    // Undoes instructions until the instruction count is back to the given
+    // value. Returns false, leaving the machine at the oldest state it can
+    // reach, if the history does not go back that far.
+    pub fn rewind_to(&mut self, instructions: u64) -> bool {
+        while self.instructions > instructions {
+            if !self.step_back() {
+                return false;
+            }
+        }
+
+        self.instructions == instructions
+    }
+
+    // The machine keeps each instruction it decodes, so that running it again
+    // skips fetching its parameters and working out their modes. Writes to
+    // an instruction's cells drop it from the cache. It is on by default;
+    // turning it off discards it.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache = if enabled {
+            Some(DecodeCache::default())
+        } else {
+            None
+        };
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    pub fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    // The number of instructions executed so far
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn rb(&self) -> i64 {
+        self.rb
+    }
+
+    // Moves the machine to another instruction, for hosts which execute
+    // some instructions themselves, such as transpiled programs
+    pub fn set_ip(&mut self, ip: usize) {
+        self.ip = ip;
+    }
+
+    pub fn set_rb(&mut self, rb: i64) {
+        self.rb = rb;
+    }
+
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    // The subroutines the program is in, outermost first, as inferred from
+    // its use of the relative base. A snapshot does not keep the stack, so
+    // a machine resumed from one starts with it empty.
+    pub fn call_stack(&self) -> &[Frame] {
+        self.stack.frames()
+    }
+
+    // The call stack at the current ip, in a form fit for an error report
+    pub fn backtrace(&self) -> String {
+        self.stack.backtrace(self.ip)
+    }
+
+    // Decodes the instruction at the given address in the machine's current
+    // memory, or returns the cell as data if it does not hold one
+    pub fn decode(&self, address: usize) -> Line {
+        let cell = |address| {
+            if address < self.memory.len() {
+                Some(self.memory.get(address))
+            } else {
+                None
+            }
+        };
+
+        decode_with(cell, address).unwrap_or(Line::Data {
+            address,
+            value: self.memory.get(address),
+        })
+    }
+
+    pub fn input(&self) -> &I {
+        &self.input
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    pub fn into_io(self) -> (I, O) {
+        (self.input, self.output)
+    }
+
+    fn get_opcode(&self) -> Result<i64, IntcodeError> {
+        if self.ip < self.memory.len() {
+            Ok(self.memory.get(self.ip))
+        } else {
+            Err(IntcodeError::MissingInstruction { ip: self.ip })
+        }
+    }
+
+    // Fetches and decodes the instruction at ip, without resolving its
+    // parameters
+    fn decode_current(&self) -> Result<Decoded, IntcodeError> {
+        let instruction = self.get_opcode()?;
+        let opcode = match Opcode::from_instruction(instruction) {
+            Some(opcode) => opcode,
+            None => {
+                return Err(IntcodeError::UnknownOpcode {
+                    ip: self.ip,
+                    instruction,
+                })
+            }
+        };
+
+        let mut decoded = Decoded {
+            instruction,
+            opcode,
+            modes: [Mode::default(); 3],
+            integers: [0; 3],
+        };
+        for offset in 1..=opcode.parameter_count() {
+            decoded.integers[offset - 1] = if self.ip + offset < self.memory.len() {
+                self.memory.get(self.ip + offset)
+            } else {
+                return Err(IntcodeError::MissingParameter {
+                    ip: self.ip,
+                    instruction,
+                    offset,
+                });
+            };
+
+            let mode_value = Mode::digit_of(instruction, offset);
+            decoded.modes[offset - 1] = match Mode::from_digit(mode_value) {
+                Some(mode) => mode,
+                None => {
+                    return Err(IntcodeError::UnsupportedMode {
+                        ip: self.ip,
+                        instruction,
+                        offset,
+                        mode: mode_value,
+                    })
+                }
+            };
+        }
+
+        Ok(decoded)
+    }
+
+    fn get_parameter(
+        &self,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+        mode: Mode,
+    ) -> Result<Parameter, IntcodeError> {
+        let address = match mode {
+            Mode::Position => integer,
+            Mode::Immediate => (self.ip + offset) as i64,
+            Mode::Relative => self.relative(instruction, offset, integer)?,
+        };
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                ip: self.ip,
+                instruction,
+                offset,
+                address,
+            });
+        }
+        let address = address as usize;
+        if let Some(max_address) = self.limits.max_address {
+            if address > max_address {
+                return Err(IntcodeError::AddressLimit {
+                    ip: self.ip,
+                    instruction,
+                    offset,
+                    address,
+                });
+            }
+        }
+
+        let value = match mode {
+            Mode::Immediate => integer,
+            Mode::Position | Mode::Relative => self.get_value(address),
+        };
+
+        Ok(Parameter {
+            integer,
+            mode,
+            address,
+            value,
+        })
+    }
+
+    // Stops with WideValue if a cell's value, read under widened arithmetic,
+    // is beyond an i64 where one is needed
+    fn narrow(&self, instruction: i64, offset: usize, value: i128) -> Result<(), IntcodeError> {
+        if i64::try_from(value).is_ok() {
+            Ok(())
+        } else {
+            Err(IntcodeError::WideValue {
+                ip: self.ip,
+                instruction,
+                offset,
+                value,
+            })
+        }
+    }
+
+    // The whole values of an instruction's parameters under widened
+    // arithmetic. The instruction's own cells must fit an i64, as must an
+    // output, a jump target or a relative base adjustment.
+    fn wide_values(
+        &self,
+        instruction: i64,
+        opcode: Opcode,
+        parameters: &[Parameter],
+    ) -> Result<[i128; 3], IntcodeError> {
+        for offset in 1..=parameters.len() {
+            self.narrow(instruction, offset, self.memory.get_wide(self.ip + offset))?;
+        }
+        let mut values = [0; 3];
+        for (value, parameter) in values.iter_mut().zip(parameters) {
+            *value = self.memory.get_wide(parameter.address);
+        }
+        match opcode {
+            Opcode::Output | Opcode::AdjustRelativeBase => {
+                self.narrow(instruction, 1, values[0])?
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => self.narrow(instruction, 2, values[1])?,
+            _ => (),
+        }
+
+        Ok(values)
+    }
+
+    // The relative base moved by integer, for a relative mode parameter or
+    // a relative base adjustment
+    fn relative(&self, instruction: i64, offset: usize, integer: i64) -> Result<i64, IntcodeError> {
+        self.rb
+            .checked_add(integer)
+            .ok_or(IntcodeError::RelativeOverflow {
+                ip: self.ip,
+                instruction,
+                offset,
+                rb: self.rb,
+                integer,
+            })
+    }
+
+    fn get_jump_target(
+        &self,
+        instruction: i64,
+        parameter: &Parameter,
+    ) -> Result<usize, IntcodeError> {
+        if parameter.value < 0 {
+            Err(IntcodeError::NegativeJump {
+                ip: self.ip,
+                instruction,
+                offset: 2,
+                target: parameter.value,
+            })
+        } else {
+            Ok(parameter.value as usize)
+        }
+    }
+
+    // The next recorded input, if a replay has one next
+    fn replay_input(&mut self, instruction: i64) -> Result<Option<i64>, IntcodeError> {
+        let replay = match self.replay.as_mut() {
+            Some(replay) => replay,
+            None => return Ok(None),
+        };
+
+        match replay.next() {
+            Some((_, TranscriptEvent::Input(value))) => {
+                replay.advance();
+                Ok(Some(value))
+            }
+            Some((event, expected)) => Err(IntcodeError::ReplayMismatch {
+                ip: self.ip,
+                instruction,
+                event,
+                expected,
+                output: None,
+            }),
+            None => Ok(None),
+        }
+    }
+
+    // Checks an output against a replay, and returns whether the replay had
+    // it next
+    fn replay_output(&mut self, instruction: i64, value: i64) -> Result<bool, IntcodeError> {
+        let replay = match self.replay.as_mut() {
+            Some(replay) => replay,
+            None => return Ok(false),
+        };
+
+        match replay.next() {
+            Some((_, TranscriptEvent::Output(expected))) if expected == value => {
+                replay.advance();
+                Ok(true)
+            }
+            Some((event, expected)) => Err(IntcodeError::ReplayMismatch {
+                ip: self.ip,
+                instruction,
+                event,
+                expected,
+                output: Some(value),
+            }),
+            None => Ok(false),
+        }
+    }
+
+    pub fn get_value(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    pub fn set_value(&mut self, address: usize, value: i64) {
+        self.memory.set(address, value);
+        if let Some(cache) = self.cache.as_mut() {
+            cache.invalidate(address);
+        }
+    }
+
+    // A cell's whole value, which only differs from get_value for values
+    // stored by widened arithmetic
+    pub fn get_wide_value(&self, address: usize) -> i128 {
+        self.memory.get_wide(address)
+    }
+
+    fn set_wide_value(&mut self, address: usize, value: i128) {
+        self.memory.set_wide(address, value);
+        if let Some(cache) = self.cache.as_mut() {
+            cache.invalidate(address);
+        }
+    }
+
+    // Executes a single instruction. Returns None when execution can carry
+    // on, and otherwise what run_until_io would return. Output values are
+    // returned rather than written to the output sink. An error raised
+    // inside a subroutine comes with the call stack, as InSubroutine.
+    pub fn step(&mut self) -> Result<Option<StepResult>, IntcodeError> {
+        self.execute().map_err(|error| self.in_subroutine(error))
+    }
+
+    // Adds the call stack to an error raised inside a subroutine
+    fn in_subroutine(&self, error: IntcodeError) -> IntcodeError {
+        if self.stack.frames().is_empty() {
+            return error;
+        }
+
+        IntcodeError::InSubroutine {
+            ip: self.ip,
+            error: Box::new(error),
+            frames: self.stack.frames().to_vec(),
+        }
+    }
+
+    #[inline]
+    fn execute(&mut self) -> Result<Option<StepResult>, IntcodeError> {
+        if let Some(limit) = self.limits.max_instructions {
+            if self.instructions >= limit {
+                return Err(IntcodeError::InstructionLimit { ip: self.ip, limit });
+            }
+        }
+
+        let widened = self.arithmetic == Arithmetic::Widened;
+        if widened {
+            let value = self.memory.get_wide(self.ip);
+            self.narrow(value as i64, 0, value)?;
+        }
+
+        let decoded = match self.cache.as_ref().and_then(|cache| cache.get(self.ip)) {
+            Some(decoded) => decoded,
+            None => {
+                let decoded = self.decode_current()?;
+                if let Some(cache) = self.cache.as_mut() {
+                    cache.insert(self.ip, decoded);
+                }
+                decoded
+            }
+        };
+        let Decoded {
+            instruction,
+            opcode,
+            modes,
+            integers,
+        } = decoded;
+
+        let mut parameters = [Parameter::default(); 3];
+        for offset in 1..=opcode.parameter_count() {
+            parameters[offset - 1] =
+                self.get_parameter(instruction, offset, integers[offset - 1], modes[offset - 1])?;
+        }
+        let [parameter_1, parameter_2, parameter_3] = parameters;
+        let values = if widened {
+            self.wide_values(instruction, opcode, &parameters[..opcode.parameter_count()])?
+        } else {
+            parameters.map(|parameter| parameter.value.into())
+        };
+
+        let mut new_ip = self.ip + 1 + opcode.parameter_count();
+        let mut new_rb = self.rb;
+        let mut write = None;
+        // A result beyond an i64, which only widened arithmetic gives
+        let mut wide_write = None;
+        let mut input = None;
+        let mut output = None;
+        let mut replayed = false;
+
+        match opcode {
+            Opcode::Add | Opcode::Multiply => {
+                let (left, right) = (values[0], values[1]);
+                let result = if opcode == Opcode::Add {
+                    self.arithmetic.add(left, right)
+                } else {
+                    self.arithmetic.multiply(left, right)
+                };
+                match result {
+                    Some(new_value) => {
+                        write = Some((parameter_3.address, new_value as i64));
+                        if i64::try_from(new_value).is_err() {
+                            wide_write = Some(new_value);
+                        }
+                    }
+                    None => {
+                        return Err(IntcodeError::Overflow {
+                            ip: self.ip,
+                            instruction,
+                            left,
+                            right,
+                        })
+                    }
+                }
+            }
+            Opcode::Input => {
+                // Undone inputs come first, then a replay, then the source
+                let read = match self.unread.pop() {
+                    Some(integer) => Ok(Some(integer)),
+                    None => match self.replay_input(instruction)? {
+                        Some(integer) => {
+                            replayed = true;
+                            Ok(Some(integer))
+                        }
+                        None => match self.limits.input_timeout {
+                            Some(timeout) => self.input.read_timeout(timeout),
+                            None => Ok(self.input.read()),
+                        },
+                    },
+                };
+                let new_value = match read {
+                    Ok(Some(integer)) => integer,
+                    Ok(None) => return Ok(Some(StepResult::NeedsInput)),
+                    Err(Timeout) => {
+                        return Err(IntcodeError::InputTimeout {
+                            ip: self.ip,
+                            instruction,
+                        })
+                    }
+                };
+                input = Some(new_value);
+                write = Some((parameter_1.address, new_value));
+            }
+            Opcode::Output => {
+                replayed = self.replay_output(instruction, parameter_1.value)?;
+                output = Some(parameter_1.value);
+            }
+            Opcode::JumpIfTrue => {
+                if values[0] != 0 {
+                    new_ip = self.get_jump_target(instruction, &parameter_2)?;
+                }
+            }
+            Opcode::JumpIfFalse => {
+                if values[0] == 0 {
+                    new_ip = self.get_jump_target(instruction, &parameter_2)?;
+                }
+            }
+            Opcode::LessThan => {
+                let new_value = if values[0] < values[1] { 1 } else { 0 };
+                write = Some((parameter_3.address, new_value));
+            }
+            Opcode::Equal => {
+                let new_value = if values[0] == values[1] { 1 } else { 0 };
+                write = Some((parameter_3.address, new_value));
+            }
+            Opcode::AdjustRelativeBase => {
+                new_rb = self.relative(instruction, 1, parameter_1.value)?;
+            }
+            Opcode::Halt => {
+                new_ip = self.ip;
+            }
+        }
+
+        let stored = self.stack.stored();
+        let call_stack: Vec<usize> = if self.tracing {
+            self.stack
+                .frames()
+                .iter()
+                .map(|frame| frame.entry)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let (call, stack_change) = match self.stack.track(
+            self.ip,
+            opcode,
+            &parameters[..opcode.parameter_count()],
+            write,
+            new_ip,
+        ) {
+            Some((call, change)) => (Some(call), Some(change)),
+            None => (None, None),
+        };
+
+        if self.tracing {
+            let event = TraceEvent {
+                ip: self.ip,
+                rb: self.rb,
+                instruction,
+                opcode,
+                parameters: parameters[..opcode.parameter_count()].to_vec(),
+                write: write.map(|(address, new)| MemoryWrite {
+                    address,
+                    old: self.memory.get(address),
+                    new,
+                }),
+                input,
+                output,
+                new_ip,
+                new_rb,
+                call,
+                call_stack,
+            };
+            self.trace.record(&event);
+        }
+
+        if let Some(profile) = self.profile.as_mut() {
+            let halted = profile.record(
+                self.ip,
+                opcode,
+                &parameters[..opcode.parameter_count()],
+                new_ip,
+            );
+            if halted {
+                eprint!("{}", profile.report());
+            }
+        }
+
+        if let Some(code) = self.code.as_mut() {
+            code.record(
+                self.ip,
+                1 + opcode.parameter_count(),
+                write.map(|(address, new)| CodeWrite {
+                    ip: self.ip,
+                    address,
+                    old: self.memory.get(address),
+                    new,
+                    instructions: self.instructions,
+                }),
+            );
+        }
+
+        if let Some(transcript) = self.transcript.as_mut() {
+            if let Some(value) = input {
+                transcript.push(TranscriptEvent::Input(value));
+            }
+            if let Some(value) = output {
+                transcript.push(TranscriptEvent::Output(value));
+            }
+        }
+
+        if let Some(history) = self.history.as_mut() {
+            history.push(Undo {
+                ip: self.ip,
+                rb: self.rb,
+                write: write.map(|(address, _)| (address, self.memory.get_wide(address))),
+                len: self.memory.len(),
+                input,
+                replayed,
+                stack: stack_change,
+                stored,
+            });
+        }
+
+        if let Some((address, value)) = write {
+            match wide_write {
+                Some(wide) => self.set_wide_value(address, wide),
+                None => self.set_value(address, value),
+            }
+        }
+        self.ip = new_ip;
+        self.rb = new_rb;
+        self.instructions += 1;
+
+        Ok(match (opcode, output) {
+            (Opcode::Halt, _) => Some(StepResult::Halted),
+            (_, Some(value)) => Some(StepResult::Output(value)),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use std::collections::VecDeque;
+
+    const QUINE: [i64; 16] = [
+        109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ];
+
+    #[test]
+    fn rewind_restores_memory_length() {
+        let mut computer = Computer::new(&QUINE, VecDeque::new(), Vec::new(), NoTrace);
+        let original = computer.snapshot();
+        computer.set_history(true);
+        computer.run().unwrap();
+        assert_eq!(computer.memory().len(), 102);
+
+        assert!(computer.rewind_to(0));
+        assert_eq!(computer.memory().len(), 16);
+        assert_eq!(computer.snapshot().memory, original.memory);
+
+        // Running again gives the same output as the first time
+        computer.run().unwrap();
+        assert_eq!(computer.output()[..16], QUINE);
+    }
+
+    #[test]
+    fn rewind_drops_instructions_decoded_past_the_end() {
+        // Writes well past the end, then runs an instruction whose parameter
+        // is in the cells that write brought into memory
+        let program = [1101, 0, 9, 20, 1105, 1, 8, 99, 4];
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_history(true);
+        computer.step().unwrap();
+        computer.step().unwrap();
+        assert_eq!(computer.step(), Ok(Some(StepResult::Output(1101))));
+
+        assert!(computer.rewind_to(0));
+        computer.set_ip(8);
+        assert_eq!(
+            computer.step(),
+            Err(IntcodeError::MissingParameter {
+                ip: 8,
+                instruction: 4,
+                offset: 1
+            })
+        );
+    }
+
+    fn run(program: &[i64]) -> Result<(), IntcodeError> {
+        Computer::new(program, VecDeque::new(), Vec::new(), NoTrace).run()
+    }
+
+    #[test]
+    fn bad_parameters() {
+        assert_eq!(
+            run(&[1, -1, 0, 0, 99]),
+            Err(IntcodeError::NegativeAddress {
+                ip: 0,
+                instruction: 1,
+                offset: 1,
+                address: -1
+            })
+        );
+        assert_eq!(
+            run(&[109, -3, 1201, 1, 0, 0, 99]),
+            Err(IntcodeError::NegativeAddress {
+                ip: 2,
+                instruction: 1201,
+                offset: 1,
+                address: -2
+            })
+        );
+        assert_eq!(
+            run(&[1105, 1, -3, 99]),
+            Err(IntcodeError::NegativeJump {
+                ip: 0,
+                instruction: 1105,
+                offset: 2,
+                target: -3
+            })
+        );
+        assert_eq!(
+            run(&[1, 0, 0, 0, 3001, 0, 0, 0, 99]),
+            Err(IntcodeError::UnsupportedMode {
+                ip: 4,
+                instruction: 3001,
+                offset: 2,
+                mode: 3
+            })
+        );
+    }
+
+    #[test]
+    fn relative_overflow() {
+        assert_eq!(
+            run(&[109, i64::MAX, 204, 1, 99]),
+            Err(IntcodeError::RelativeOverflow {
+                ip: 2,
+                instruction: 204,
+                offset: 1,
+                rb: i64::MAX,
+                integer: 1
+            })
+        );
+        assert_eq!(
+            run(&[109, i64::MAX, 109, 1, 99]),
+            Err(IntcodeError::RelativeOverflow {
+                ip: 2,
+                instruction: 109,
+                offset: 1,
+                rb: i64::MAX,
+                integer: 1
+            })
+        );
+        assert_eq!(
+            run(&[109, i64::MIN, 209, -1, 99]),
+            Err(IntcodeError::RelativeOverflow {
+                ip: 2,
+                instruction: 209,
+                offset: 1,
+                rb: i64::MIN,
+                integer: -1
+            })
+        );
+    }
+
+    #[test]
+    fn widened_arithmetic() {
+        // 2^64 + 1 has its low 64 bits set, but 2^64 does not
+        let program = assemble(
+            "
+                    mul  #4294967296 #4294967296 x
+                    add  x #1 x
+                    add  x #-1 x
+                    jt   x #big
+                    out  #0
+                    hlt
+            big:    out  #1
+                    hlt
+            x:      data 0
+            ",
+        )
+        .unwrap();
+        let x = program.len() - 1;
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_arithmetic(Arithmetic::Widened);
+        computer.set_history(true);
+
+        computer.step().unwrap();
+        computer.step().unwrap();
+        assert_eq!(computer.get_wide_value(x), (1 << 64) + 1);
+        assert_eq!(computer.get_value(x), 1);
+
+        let snapshot: Snapshot = computer.snapshot().to_string().parse().unwrap();
+        assert_eq!(snapshot.wide, vec![(x, (1 << 64) + 1)]);
+        let resumed: Computer<_, _, PagedMemory> =
+            Computer::from_snapshot(&snapshot, VecDeque::new(), Vec::new(), NoTrace);
+        assert_eq!(resumed.get_wide_value(x), (1 << 64) + 1);
+
+        assert!(computer.step_back());
+        assert_eq!(computer.get_wide_value(x), 1 << 64);
+        computer.run().unwrap();
+        assert_eq!(computer.output(), &vec![1]);
+
+        // The same program wraps to zero by default
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.run().unwrap();
+        assert_eq!(computer.output(), &vec![0]);
+    }
+
+    #[test]
+    fn wide_values_where_an_i64_is_needed() {
+        let program = assemble(
+            "
+                    mul  #4294967296 #4294967296 target
+                    jt   #1 #target
+            target: data 0
+            ",
+        )
+        .unwrap();
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_arithmetic(Arithmetic::Widened);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::WideValue {
+                ip: 7,
+                instruction: 0,
+                offset: 0,
+                value: 1 << 64
+            })
+        );
+
+        let program = assemble(
+            "
+                    mul  #4294967296 #4294967296 target
+                    jt   #1 target
+                    hlt
+            target: data 0
+            ",
+        )
+        .unwrap();
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_arithmetic(Arithmetic::Widened);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::WideValue {
+                ip: 4,
+                instruction: 105,
+                offset: 2,
+                value: 1 << 64
+            })
+        );
+    }
+
+    #[test]
+    fn errors_in_subroutines_carry_the_call_stack() {
+        let program = assemble(
+            "
+                    arb  #stack
+                    add  #r1 #0 @0
+                    jt   #1 #f
+            r1:     hlt
+            f:      arb  #1
+                    add  #r2 #0 @0
+                    jt   #1 #g
+            r2:     arb  #-1
+                    jt   #1 @0
+            g:      data 98
+            stack:  data 0
+            ",
+        )
+        .unwrap();
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+
+        let error = computer.run().unwrap_err();
+        assert_eq!(
+            error.root(),
+            &IntcodeError::UnknownOpcode {
+                ip: 24,
+                instruction: 98
+            }
+        );
+        match &error {
+            IntcodeError::InSubroutine { ip, frames, .. } => {
+                assert_eq!(*ip, 24);
+                assert_eq!(
+                    frames
+                        .iter()
+                        .map(|frame| (frame.entry, frame.call_site))
+                        .collect::<Vec<_>>(),
+                    vec![(10, 6), (24, 16)]
+                );
+            }
+            error => panic!("no call stack in {:?}", error),
+        }
+        assert_eq!(
+            error.to_string(),
+            "Unknown opcode 98 in instruction <98> at ip 24\n\
+             at 24 in subroutine 24\n\
+             called from 16 in subroutine 10\n\
+             called from 6 in main"
+        );
+    }
+
+    #[test]
+    fn errors_in_main_are_left_alone() {
+        let mut computer = Computer::new(&[3, 0, 99], VecDeque::new(), Vec::new(), NoTrace);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::InputUnavailable {
+                ip: 0,
+                instruction: 3
+            })
+        );
+    }
+
+    fn limited(program: &[i64], limits: Limits) -> Result<(), IntcodeError> {
+        let mut computer = Computer::new(program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_limits(limits);
+        computer.run()
+    }
+
+    #[test]
+    fn address_limit() {
+        let limits = Limits {
+            max_address: Some(20),
+            ..Limits::default()
+        };
+        assert_eq!(
+            limited(&[1, 50, 0, 0, 99], limits),
+            Err(IntcodeError::AddressLimit {
+                ip: 0,
+                instruction: 1,
+                offset: 1,
+                address: 50
+            })
+        );
+        assert_eq!(
+            limited(&[1101, 1, 2, 21, 99], limits),
+            Err(IntcodeError::AddressLimit {
+                ip: 0,
+                instruction: 1101,
+                offset: 3,
+                address: 21
+            })
+        );
+        assert_eq!(limited(&[1101, 1, 2, 20, 99], limits), Ok(()));
+    }
+
+    #[test]
+    fn input_timeout() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut computer = Computer::new(&[3, 0, 99], receiver, Vec::new(), NoTrace);
+        computer.set_limits(Limits {
+            input_timeout: Some(Duration::from_millis(10)),
+            ..Limits::default()
+        });
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::InputTimeout {
+                ip: 0,
+                instruction: 3
+            })
+        );
+
+        // Once the sender has gone there is nothing to wait for
+        drop(sender);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::InputUnavailable {
+                ip: 0,
+                instruction: 3
+            })
+        );
+    }
+
+    #[test]
+    fn forks_run_apart() {
+        // Calls a subroutine which multiplies two inputs
+        let program = assemble(
+            "
+                    arb  #100
+                    add  #ret #0 @0
+                    jt   #1 #sub
+            ret:    hlt
+            sub:    in   a
+                    in   b
+                    mul  a b a
+                    out  a
+                    jt   #1 @0
+            a:      data 0
+            b:      data 0
+            ",
+        )
+        .unwrap();
+        let mut parent = Computer::new(&program, VecDeque::from([3]), Vec::new(), NoTrace);
+        parent.set_history(true);
+        for _ in 0..4 {
+            parent.step().unwrap();
+        }
+
+        // The input read by the step taken back is read again by a fork
+        assert!(parent.step_back());
+        let frames = parent.call_stack().to_vec();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].entry, 10);
+
+        let mut child = parent.fork_with(VecDeque::from([10]), Vec::new());
+        assert_eq!(child.ip(), 10);
+        assert_eq!(child.call_stack(), frames);
+        assert_eq!(child.run_until_io(), Ok(StepResult::Output(30)));
+        assert_eq!(child.call_stack(), frames);
+        child.run().unwrap();
+        assert_eq!(child.call_stack(), &[]);
+        assert_eq!(child.get_value(23), 30);
+
+        assert_eq!(parent.ip(), 10);
+        assert_eq!(parent.get_value(23), 0);
+        let mut copy = parent.fork();
+        parent.input_mut().push_back(5);
+        parent.run().unwrap();
+        assert_eq!(parent.output(), &[15]);
+        assert_eq!(parent.get_value(23), 15);
+
+        // A plain fork shares nothing added to the parent afterwards
+        copy.input_mut().push_back(7);
+        copy.run().unwrap();
+        assert_eq!(copy.output(), &[21]);
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt;
+
+use crate::instruction::{Mode, Opcode};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operand {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Operand {
+    fn new(mode: Mode, integer: i64) -> Operand {
+        match mode {
+            Mode::Position => Operand::Position(integer),
+            Mode::Immediate => Operand::Immediate(integer),
+            Mode::Relative => Operand::Relative(integer),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Position(integer) => write!(f, "[P|{}]", integer),
+            Operand::Immediate(integer) => write!(f, "[I|{}]", integer),
+            Operand::Relative(integer) => write!(f, "[R|{}]", integer),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Line {
+    Instruction {
+        address: usize,
+        opcode: Opcode,
+        operands: Vec<Operand>,
+    },
+    Data {
+        address: usize,
+        value: i64,
+    },
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        match self {
+            Line::Instruction { address, .. } | Line::Data { address, .. } => *address,
+        }
+    }
+
+    // The number of cells this line occupies
+    pub fn len(&self) -> usize {
+        match self {
+            Line::Instruction { operands, .. } => 1 + operands.len(),
+            Line::Data { .. } => 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Instruction {
+                address,
+                opcode,
+                operands,
+            } => {
+                let mut text = format!("{:>5}: {:<4}", address, opcode.mnemonic());
+                for operand in operands {
+                    text.push_str(&format!(" {}", operand));
+                }
+                write!(f, "{}", text.trim_end())
+            }
+            Line::Data { address, value } => write!(f, "{:>5}: data {}", address, value),
+        }
+    }
+}
+
+// Decodes the instruction at the given address. Returns None when the cell
+// does not hold a well formed instruction: an unknown opcode, an unsupported
+// mode, a mode digit beyond the last parameter, or parameters which run past
+// the end of the program.
+pub fn decode(program: &[i64], address: usize) -> Option<Line> {
+    decode_with(|address| program.get(address).copied(), address)
+}
+
+// As decode, reading cells through a function which returns None past the
+// end of the program
+pub fn decode_with<F: Fn(usize) -> Option<i64>>(cell: F, address: usize) -> Option<Line> {
+    let instruction = cell(address)?;
+    if instruction < 0 {
+        return None;
+    }
+
+    let opcode = Opcode::from_instruction(instruction)?;
+    let parameter_count = opcode.parameter_count();
+    if instruction / 10_i64.pow(2 + parameter_count as u32) != 0 {
+        return None;
+    }
+
+    let mut operands = Vec::new();
+    for offset in 1..=parameter_count {
+        let mode = Mode::from_digit(Mode::digit_of(instruction, offset))?;
+        let integer = cell(address + offset)?;
+        operands.push(Operand::new(mode, integer));
+    }
+
+    Some(Line::Instruction {
+        address,
+        opcode,
+        operands,
+    })
+}
+
+// Decodes the program in a single sweep from address 0. Cells which cannot
+// be decoded as an instruction are listed as data, and the sweep resumes at
+// the next cell.
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        let line = decode(program, address).unwrap_or(Line::Data {
+            address,
+            value: program[address],
+        });
+        address += line.len();
+        lines.push(line);
+    }
+
+    lines
+}
+
+pub fn listing(program: &[i64]) -> String {
+    let mut result = String::new();
+
+    for line in disassemble(program) {
+        result.push_str(&format!("{}\n", line));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUINE: [i64; 16] = [
+        109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ];
+
+    #[test]
+    fn decodes_each_mode() {
+        assert_eq!(
+            decode(&[21001, 5, -6, 7], 0),
+            Some(Line::Instruction {
+                address: 0,
+                opcode: Opcode::Add,
+                operands: vec![
+                    Operand::Position(5),
+                    Operand::Immediate(-6),
+                    Operand::Relative(7),
+                ],
+            })
+        );
+        assert_eq!(
+            decode(&[0, 99], 1),
+            Some(Line::Instruction {
+                address: 1,
+                opcode: Opcode::Halt,
+                operands: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn cells_which_are_not_instructions() {
+        // An unknown opcode, a negative cell, an unsupported mode, a mode
+        // past the last parameter, and parameters past the end
+        for program in [&[42][..], &[-1], &[301, 0], &[199], &[1101, 1, 2], &[4]] {
+            assert_eq!(decode(program, 0), None, "{:?}", program);
+        }
+        assert_eq!(decode(&[99], 1), None);
+    }
+
+    #[test]
+    fn data_between_instructions() {
+        assert_eq!(
+            disassemble(&[104, 7, 301, 99, 1, 0]),
+            vec![
+                Line::Instruction {
+                    address: 0,
+                    opcode: Opcode::Output,
+                    operands: vec![Operand::Immediate(7)],
+                },
+                Line::Data {
+                    address: 2,
+                    value: 301,
+                },
+                Line::Instruction {
+                    address: 3,
+                    opcode: Opcode::Halt,
+                    operands: vec![],
+                },
+                Line::Data {
+                    address: 4,
+                    value: 1,
+                },
+                Line::Data {
+                    address: 5,
+                    value: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn quine_listing() {
+        assert_eq!(
+            listing(&QUINE),
+            "    0: arb  [I|1]
+    2: out  [R|-1]
+    4: add  [P|100] [I|1] [P|100]
+    8: eq   [P|100] [I|16] [P|101]
+   12: jf   [P|101] [I|0]
+   15: hlt
+"
+        );
+        assert_eq!(
+            listing(&[1, 0, 0, 0, 2]),
+            "    0: add  [P|0] [P|0] [P|0]\n    4: data 2\n"
+        );
+    }
+}
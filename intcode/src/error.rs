@@ -0,0 +1,399 @@
+use std::error;
+use std::fmt;
+
+use crate::stack::{backtrace, Frame};
+use crate::transcript::TranscriptEvent;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum IntcodeError {
+    MissingInstruction {
+        ip: usize,
+    },
+    UnknownOpcode {
+        ip: usize,
+        instruction: i64,
+    },
+    MissingParameter {
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+    },
+    UnsupportedMode {
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        mode: i64,
+    },
+    NegativeAddress {
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        address: i64,
+    },
+    NegativeJump {
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        target: i64,
+    },
+    // A relative address or relative base adjustment beyond the range of
+    // a cell
+    RelativeOverflow {
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        rb: i64,
+        integer: i64,
+    },
+    InputUnavailable {
+        ip: usize,
+        instruction: i64,
+    },
+    InstructionLimit {
+        ip: usize,
+        limit: u64,
+    },
+    AddressLimit {
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        address: usize,
+    },
+    InputTimeout {
+        ip: usize,
+        instruction: i64,
+    },
+    Overflow {
+        ip: usize,
+        instruction: i64,
+        left: i128,
+        right: i128,
+    },
+    // A value beyond the range of an i64, which only widened arithmetic
+    // stores, used where an i64 is needed. Offset 0 is the instruction.
+    WideValue {
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        value: i128,
+    },
+    ReplayMismatch {
+        ip: usize,
+        instruction: i64,
+        event: usize,
+        expected: TranscriptEvent,
+        output: Option<i64>,
+    },
+    // Text sent to an ASCII program which has a character outside ASCII
+    NonAsciiInput {
+        character: char,
+    },
+    // An error raised inside a subroutine, with the call stack at the
+    // time, outermost frame first
+    InSubroutine {
+        ip: usize,
+        error: Box<IntcodeError>,
+        frames: Vec<Frame>,
+    },
+}
+
+impl IntcodeError {
+    // The error itself, without the call stack it was raised in
+    pub fn root(&self) -> &IntcodeError {
+        match self {
+            IntcodeError::InSubroutine { error, .. } => error.root(),
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IntcodeError::*;
+
+        match self {
+            MissingInstruction { ip } => write!(f, "Instruction not found at ip {}", ip),
+            UnknownOpcode { ip, instruction } => write!(
+                f,
+                "Unknown opcode {} in instruction <{}> at ip {}",
+                instruction % 100,
+                instruction,
+                ip
+            ),
+            MissingParameter {
+                ip,
+                instruction,
+                offset,
+            } => write!(
+                f,
+                "Parameter not found at offset {} of instruction <{}> at ip {}",
+                offset, instruction, ip
+            ),
+            UnsupportedMode {
+                ip,
+                instruction,
+                offset,
+                mode,
+            } => write!(
+                f,
+                "Unsupported mode {} for offset {} of instruction <{}> at ip {}",
+                mode, offset, instruction, ip
+            ),
+            NegativeAddress {
+                ip,
+                instruction,
+                offset,
+                address,
+            } => write!(
+                f,
+                "Negative address {} for offset {} of instruction <{}> at ip {}",
+                address, offset, instruction, ip
+            ),
+            NegativeJump {
+                ip,
+                instruction,
+                offset,
+                target,
+            } => write!(
+                f,
+                "Negative jump target {} for offset {} of instruction <{}> at ip {}",
+                target, offset, instruction, ip
+            ),
+            RelativeOverflow {
+                ip,
+                instruction,
+                offset,
+                rb,
+                integer,
+            } => write!(
+                f,
+                "Relative base {} plus {} overflows for offset {} of instruction <{}> at ip {}",
+                rb, integer, offset, instruction, ip
+            ),
+            InputUnavailable { ip, instruction } => write!(
+                f,
+                "Input not available for instruction <{}> at ip {}",
+                instruction, ip
+            ),
+            InstructionLimit { ip, limit } => {
+                write!(f, "Instruction limit {} reached at ip {}", limit, ip)
+            }
+            AddressLimit {
+                ip,
+                instruction,
+                offset,
+                address,
+            } => write!(
+                f,
+                "Address {} beyond the memory limit for offset {} of instruction <{}> at ip {}",
+                address, offset, instruction, ip
+            ),
+            InputTimeout { ip, instruction } => write!(
+                f,
+                "Timed out waiting for input for instruction <{}> at ip {}",
+                instruction, ip
+            ),
+            Overflow {
+                ip,
+                instruction,
+                left,
+                right,
+            } => write!(
+                f,
+                "Result for {} and {} does not fit a cell in instruction <{}> at ip {}",
+                left, right, instruction, ip
+            ),
+            WideValue {
+                ip,
+                instruction,
+                offset,
+                value,
+            } => write!(
+                f,
+                "Value {} too wide for offset {} of instruction <{}> at ip {}",
+                value, offset, instruction, ip
+            ),
+            ReplayMismatch {
+                ip,
+                instruction,
+                event,
+                expected,
+                output,
+            } => {
+                let found = match output {
+                    Some(value) => format!("output {}", value),
+                    None => String::from("a read of input"),
+                };
+                write!(
+                    f,
+                    "Replay expected {} as event {} but found {} in instruction <{}> at ip {}",
+                    expected, event, found, instruction, ip
+                )
+            }
+            NonAsciiInput { character } => write!(
+                f,
+                "Cannot send {:?} (U+{:04X}) to an ASCII program",
+                character, *character as u32
+            ),
+            InSubroutine { ip, error, frames } => {
+                write!(f, "{}\n{}", error, backtrace(*ip, frames))
+            }
+        }
+    }
+}
+
+impl error::Error for IntcodeError {}
+
+// Errors carry the 1-based line number of the offending source line
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AssembleError {
+    UnknownMnemonic {
+        line: usize,
+        mnemonic: String,
+    },
+    OperandCount {
+        line: usize,
+        mnemonic: String,
+        expected: usize,
+        found: usize,
+    },
+    InvalidOperand {
+        line: usize,
+        operand: String,
+    },
+    InvalidLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use AssembleError::*;
+
+        match self {
+            UnknownMnemonic { line, mnemonic } => {
+                write!(f, "Unknown mnemonic {} on line {}", mnemonic, line)
+            }
+            OperandCount {
+                line,
+                mnemonic,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} takes {} operands but {} were given on line {}",
+                mnemonic, expected, found, line
+            ),
+            InvalidOperand { line, operand } => {
+                write!(f, "Invalid operand {} on line {}", operand, line)
+            }
+            InvalidLabel { line, label } => write!(f, "Invalid label {} on line {}", label, line),
+            DuplicateLabel { line, label } => {
+                write!(f, "Label {} redefined on line {}", label, line)
+            }
+            UndefinedLabel { line, label } => {
+                write!(f, "Undefined label {} on line {}", label, line)
+            }
+        }
+    }
+}
+
+impl error::Error for AssembleError {}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SnapshotError {
+    InvalidLine { line: usize, text: String },
+    MissingField { name: &'static str },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::InvalidLine { line, text } => {
+                write!(f, "Invalid snapshot line {}: {}", line, text)
+            }
+            SnapshotError::MissingField { name } => write!(f, "Snapshot has no {} line", name),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {}
+
+// Errors from reading a program written as comma separated integers
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseError {
+    InvalidInteger { index: usize, text: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidInteger { index, text } => {
+                write!(
+                    f,
+                    "Invalid integer {:?} at position {} of program",
+                    text, index
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TranscriptError {
+    MissingHeader,
+    InvalidLine { line: usize, text: String },
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscriptError::MissingHeader => write!(f, "Transcript has no header line"),
+            TranscriptError::InvalidLine { line, text } => {
+                write!(f, "Invalid transcript line {}: {}", line, text)
+            }
+        }
+    }
+}
+
+impl error::Error for TranscriptError {}
+
+// Errors from running many machines together. Machines are identified by
+// the index they were added with.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ScheduleError {
+    Machine { index: usize, error: IntcodeError },
+    Deadlock { blocked: Vec<usize> },
+    UnknownAddress { index: usize, address: i64 },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Machine { index, error } => write!(f, "Machine {}: {}", index, error),
+            ScheduleError::Deadlock { blocked } => write!(
+                f,
+                "Deadlock: machines {:?} are all waiting for input",
+                blocked
+            ),
+            ScheduleError::UnknownAddress { index, address } => write!(
+                f,
+                "Machine {} sent a packet to unknown address {}",
+                index, address
+            ),
+        }
+    }
+}
+
+impl error::Error for ScheduleError {}
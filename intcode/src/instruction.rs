@@ -0,0 +1,166 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Mode {
+    #[default]
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    pub fn from_digit(digit: i64) -> Option<Mode> {
+        match digit {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn digit(&self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+
+    // The mode digit for the parameter at the given offset (1 to 3)
+    pub fn digit_of(instruction: i64, offset: usize) -> i64 {
+        match offset {
+            1 => (instruction / 100) % 10,
+            2 => (instruction / 1000) % 10,
+            3 => (instruction / 10000) % 10,
+            _ => unreachable!("Instructions have at most three parameters"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equal,
+    AdjustRelativeBase,
+    Halt,
+}
+
+impl Opcode {
+    pub fn from_instruction(instruction: i64) -> Option<Opcode> {
+        match instruction % 100 {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Multiply),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpIfTrue),
+            6 => Some(Opcode::JumpIfFalse),
+            7 => Some(Opcode::LessThan),
+            8 => Some(Opcode::Equal),
+            9 => Some(Opcode::AdjustRelativeBase),
+            99 => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        Opcode::all()
+            .into_iter()
+            .find(|opcode| opcode.mnemonic() == mnemonic)
+    }
+
+    pub fn all() -> [Opcode; 10] {
+        use Opcode::*;
+
+        [
+            Add,
+            Multiply,
+            Input,
+            Output,
+            JumpIfTrue,
+            JumpIfFalse,
+            LessThan,
+            Equal,
+            AdjustRelativeBase,
+            Halt,
+        ]
+    }
+
+    pub fn code(&self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equal => 8,
+            Opcode::AdjustRelativeBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Multiply => "mul",
+            Opcode::Input => "in",
+            Opcode::Output => "out",
+            Opcode::JumpIfTrue => "jt",
+            Opcode::JumpIfFalse => "jf",
+            Opcode::LessThan => "lt",
+            Opcode::Equal => "eq",
+            Opcode::AdjustRelativeBase => "arb",
+            Opcode::Halt => "hlt",
+        }
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equal => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    // The offset of the parameter this instruction writes to, if any
+    pub fn write_offset(&self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equal => Some(3),
+            Opcode::Input => Some(1),
+            _ => None,
+        }
+    }
+}
+
+// A parameter as decoded during execution: the integer stored in the
+// instruction, the address it refers to (for immediate mode, the address of
+// the parameter itself) and the value found there.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Parameter {
+    pub integer: i64,
+    pub mode: Mode,
+    pub address: usize,
+    pub value: i64,
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self.mode {
+                Mode::Position => format!("[P|{}|{}]", self.address, self.value),
+                Mode::Immediate => format!("[I|{}]", self.value),
+                Mode::Relative => format!("[R|{}|{}]", self.address, self.value),
+            }
+        )
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+// Returned by Input::read_timeout when no value arrived in time
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Timeout;
+
+// A source of values for opcode 3. None means that no value is
+// available, and never will be.
+pub trait Input {
+    fn read(&mut self) -> Option<i64>;
+
+    // As read, but gives up waiting after the timeout. Sources which never
+    // wait need not override this.
+    fn read_timeout(&mut self, _timeout: Duration) -> Result<Option<i64>, Timeout> {
+        Ok(self.read())
+    }
+
+    // Values which have been supplied but not yet read, where the source
+    // can report them without consuming them
+    fn pending(&self) -> Vec<i64> {
+        Vec::new()
+    }
+
+    // Puts back values which were pending, such as those in a snapshot, to
+    // be read before anything supplied since. Returns false if the source
+    // cannot hold them.
+    fn restore(&mut self, _values: &[i64]) -> bool {
+        false
+    }
+}
+
+// A sink for values produced by opcode 4.
+pub trait Output {
+    fn write(&mut self, value: i64);
+
+    // Values which have been written but not yet taken by the host, where
+    // the sink holds on to them
+    fn pending(&self) -> Vec<i64> {
+        Vec::new()
+    }
+
+    // Puts back values which were pending, ahead of anything written since.
+    // Returns false if the sink cannot hold them.
+    fn restore(&mut self, _values: &[i64]) -> bool {
+        false
+    }
+}
+
+impl Input for Receiver<i64> {
+    fn read(&mut self) -> Option<i64> {
+        self.recv().ok()
+    }
+
+    fn read_timeout(&mut self, timeout: Duration) -> Result<Option<i64>, Timeout> {
+        match self.recv_timeout(timeout) {
+            Ok(value) => Ok(Some(value)),
+            Err(RecvTimeoutError::Timeout) => Err(Timeout),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+}
+
+impl Output for Sender<i64> {
+    fn write(&mut self, value: i64) {
+        let _ = self.send(value);
+    }
+}
+
+impl Input for VecDeque<i64> {
+    fn read(&mut self) -> Option<i64> {
+        self.pop_front()
+    }
+
+    fn pending(&self) -> Vec<i64> {
+        self.iter().copied().collect()
+    }
+
+    fn restore(&mut self, values: &[i64]) -> bool {
+        for &value in values.iter().rev() {
+            self.push_front(value);
+        }
+        true
+    }
+}
+
+impl Output for VecDeque<i64> {
+    fn write(&mut self, value: i64) {
+        self.push_back(value);
+    }
+
+    fn pending(&self) -> Vec<i64> {
+        self.iter().copied().collect()
+    }
+
+    fn restore(&mut self, values: &[i64]) -> bool {
+        for &value in values.iter().rev() {
+            self.push_front(value);
+        }
+        true
+    }
+}
+
+impl Output for Vec<i64> {
+    fn write(&mut self, value: i64) {
+        self.push(value);
+    }
+
+    fn pending(&self) -> Vec<i64> {
+        self.clone()
+    }
+
+    fn restore(&mut self, values: &[i64]) -> bool {
+        self.splice(0..0, values.iter().copied());
+        true
+    }
+}
+
+impl<T: Input + ?Sized> Input for &mut T {
+    fn read(&mut self) -> Option<i64> {
+        (**self).read()
+    }
+
+    fn read_timeout(&mut self, timeout: Duration) -> Result<Option<i64>, Timeout> {
+        (**self).read_timeout(timeout)
+    }
+
+    fn pending(&self) -> Vec<i64> {
+        (**self).pending()
+    }
+
+    fn restore(&mut self, values: &[i64]) -> bool {
+        (**self).restore(values)
+    }
+}
+
+impl<T: Output + ?Sized> Output for &mut T {
+    fn write(&mut self, value: i64) {
+        (**self).write(value)
+    }
+
+    fn pending(&self) -> Vec<i64> {
+        (**self).pending()
+    }
+
+    fn restore(&mut self, values: &[i64]) -> bool {
+        (**self).restore(values)
+    }
+}
+
+// Wraps a closure which supplies input values
+pub struct InputFn<F>(pub F);
+
+impl<F: FnMut() -> Option<i64>> Input for InputFn<F> {
+    fn read(&mut self) -> Option<i64> {
+        (self.0)()
+    }
+}
+
+// Wraps a closure which receives output values
+pub struct OutputFn<F>(pub F);
+
+impl<F: FnMut(i64)> Output for OutputFn<F> {
+    fn write(&mut self, value: i64) {
+        (self.0)(value)
+    }
+}
+
+// Wraps an iterator which supplies input values
+pub struct InputIter<I>(pub I);
+
+impl<I: Iterator<Item = i64>> Input for InputIter<I> {
+    fn read(&mut self) -> Option<i64> {
+        self.0.next()
+    }
+}
@@ -0,0 +1,45 @@
+mod ascii;
+mod assembler;
+mod cache;
+mod cfg;
+mod computer;
+mod disassembler;
+mod error;
+mod instruction;
+mod io;
+mod memory;
+mod modification;
+mod network;
+mod pipeline;
+mod profile;
+mod program;
+mod scheduler;
+mod snapshot;
+mod stack;
+mod table;
+mod trace;
+mod transcript;
+mod transpiler;
+
+pub use ascii::{Ascii, AsciiOutput};
+pub use assembler::{assemble, to_source};
+pub use cfg::{Block, ControlFlowGraph, Edge, EdgeKind};
+pub use computer::{Arithmetic, Computer, Limits, StepResult};
+pub use disassembler::{decode, decode_with, disassemble, listing, Line, Operand};
+pub use error::{
+    AssembleError, IntcodeError, ParseError, ScheduleError, SnapshotError, TranscriptError,
+};
+pub use instruction::{Mode, Opcode, Parameter};
+pub use io::{Input, InputFn, InputIter, Output, OutputFn, Timeout};
+pub use memory::{Memory, PagedMemory, SparseMemory};
+pub use modification::CodeWrite;
+pub use network::{Network, Packet, NAT};
+pub use pipeline::{BestPhases, Pipeline, PipelineOutput, Topology};
+pub use profile::{BasicBlock, HotLoop, Profile};
+pub use program::parse_program;
+pub use scheduler::{Machine, Scheduler, Traffic};
+pub use snapshot::Snapshot;
+pub use stack::{CallEvent, Frame};
+pub use trace::{JsonTrace, MemoryWrite, NoTrace, TextTrace, TraceEvent, TraceSink};
+pub use transcript::{Session, Transcript, TranscriptEvent};
+pub use transpiler::transpile;
@@ -0,0 +1,362 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::table::IndexTable;
+
+// Storage for a Computer's memory. Cells which have never been written
+// read as zero. The length is one past the highest address written so
+// far, and addresses at or beyond it hold no instructions or parameters.
+pub trait Memory {
+    fn from_program(program: &[i64]) -> Self
+    where
+        Self: Sized;
+
+    fn get(&self, address: usize) -> i64;
+
+    fn set(&mut self, address: usize, value: i64);
+
+    fn len(&self) -> usize;
+
+    // Shortens memory to len cells, so that the cells from len on read as
+    // zero again. Does nothing if memory is already no longer than len.
+    fn truncate(&mut self, len: usize);
+
+    // The cells which may have been written, as runs of consecutive cells
+    // in address order, each with the address of its first cell. Cells
+    // outside the runs read as zero, and the last run ends at len.
+    fn runs(&self) -> Vec<(usize, Vec<i64>)>;
+
+    // A cell's whole value. Only widened arithmetic writes values beyond
+    // the range of an i64, through set_wide; get gives the low 64 bits of
+    // those, and set replaces them.
+    fn get_wide(&self, address: usize) -> i128;
+
+    fn set_wide(&mut self, address: usize, value: i128);
+
+    // The cells which hold values beyond the range of an i64, in address
+    // order
+    fn wide_cells(&self) -> Vec<(usize, i128)>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+const PAGE_BITS: usize = 10;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+const PAGE_MASK: usize = PAGE_SIZE - 1;
+
+type Page = [i64; PAGE_SIZE];
+
+// Pages below this index are found through a table rather than a HashMap
+const DENSE_PAGES: usize = 1 << 10;
+
+// Memory held in fixed size pages which are allocated on first write, so
+// that a program which touches a few distant addresses stays small. Pages
+// are shared between clones and copied on write, so cloning only copies
+// the page table.
+#[derive(Clone, Default)]
+pub struct PagedMemory {
+    pages: IndexTable<Arc<Page>, DENSE_PAGES>,
+    wide: BTreeMap<usize, i128>,
+    len: usize,
+}
+
+impl PagedMemory {
+    #[inline]
+    fn page_mut(&mut self, page_index: usize) -> &mut Arc<Page> {
+        self.pages
+            .get_or_insert_with(page_index, || Arc::new([0; PAGE_SIZE]))
+    }
+}
+
+impl Memory for PagedMemory {
+    fn from_program(program: &[i64]) -> PagedMemory {
+        let mut memory = PagedMemory::default();
+
+        for (index, &integer) in program.iter().enumerate() {
+            memory.set(index, integer);
+        }
+
+        memory
+    }
+
+    #[inline]
+    fn get(&self, address: usize) -> i64 {
+        match self.pages.get(address >> PAGE_BITS) {
+            Some(page) => page[address & PAGE_MASK],
+            None => 0,
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, address: usize, value: i64) {
+        let page = self.page_mut(address >> PAGE_BITS);
+        Arc::make_mut(page)[address & PAGE_MASK] = value;
+
+        if address >= self.len {
+            self.len = address + 1;
+        }
+        if !self.wide.is_empty() {
+            self.wide.remove(&address);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        self.wide.split_off(&len);
+        let first_page = len.div_ceil(PAGE_SIZE);
+        self.pages.truncate(first_page);
+        if !len.is_multiple_of(PAGE_SIZE) {
+            if let Some(page) = self.pages.get_mut(len >> PAGE_BITS) {
+                Arc::make_mut(page)[len & PAGE_MASK..].fill(0);
+            }
+        }
+        self.len = len;
+    }
+
+    fn runs(&self) -> Vec<(usize, Vec<i64>)> {
+        let mut runs: Vec<(usize, Vec<i64>)> = Vec::new();
+        for (page_index, page) in self.pages.iter() {
+            let start = page_index << PAGE_BITS;
+            let cells = &page[..PAGE_SIZE.min(self.len - start)];
+            match runs.last_mut() {
+                Some((address, run)) if *address + run.len() == start => {
+                    run.extend_from_slice(cells)
+                }
+                _ => runs.push((start, cells.to_vec())),
+            }
+        }
+
+        runs
+    }
+
+    fn get_wide(&self, address: usize) -> i128 {
+        match self.wide.get(&address) {
+            Some(&value) => value,
+            None => self.get(address).into(),
+        }
+    }
+
+    fn set_wide(&mut self, address: usize, value: i128) {
+        self.set(address, value as i64);
+        if i64::try_from(value).is_err() {
+            self.wide.insert(address, value);
+        }
+    }
+
+    fn wide_cells(&self) -> Vec<(usize, i128)> {
+        self.wide
+            .iter()
+            .map(|(&address, &value)| (address, value))
+            .collect()
+    }
+}
+
+// Memory held in a HashMap, one entry per cell written
+#[derive(Clone, Default)]
+pub struct SparseMemory {
+    cells: HashMap<usize, i64>,
+    wide: BTreeMap<usize, i128>,
+    len: usize,
+}
+
+impl Memory for SparseMemory {
+    fn from_program(program: &[i64]) -> SparseMemory {
+        let mut memory = SparseMemory::default();
+
+        for (index, &integer) in program.iter().enumerate() {
+            memory.set(index, integer);
+        }
+
+        memory
+    }
+
+    fn get(&self, address: usize) -> i64 {
+        if let Some(&value) = self.cells.get(&address) {
+            value
+        } else {
+            0
+        }
+    }
+
+    fn set(&mut self, address: usize, value: i64) {
+        self.cells.insert(address, value);
+
+        if address >= self.len {
+            self.len = address + 1;
+        }
+        if !self.wide.is_empty() {
+            self.wide.remove(&address);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        self.cells.retain(|&address, _| address < len);
+        self.wide.split_off(&len);
+        self.len = len;
+    }
+
+    fn runs(&self) -> Vec<(usize, Vec<i64>)> {
+        let mut cells: Vec<_> = self
+            .cells
+            .iter()
+            .map(|(&address, &value)| (address, value))
+            .collect();
+        cells.sort_unstable();
+        if self.len > 0 && !self.cells.contains_key(&(self.len - 1)) {
+            cells.push((self.len - 1, 0));
+        }
+
+        let mut runs: Vec<(usize, Vec<i64>)> = Vec::new();
+        for (address, value) in cells {
+            match runs.last_mut() {
+                Some((start, run)) if *start + run.len() == address => run.push(value),
+                _ => runs.push((address, vec![value])),
+            }
+        }
+
+        runs
+    }
+
+    fn get_wide(&self, address: usize) -> i128 {
+        match self.wide.get(&address) {
+            Some(&value) => value,
+            None => self.get(address).into(),
+        }
+    }
+
+    fn set_wide(&mut self, address: usize, value: i128) {
+        self.set(address, value as i64);
+        if i64::try_from(value).is_err() {
+            self.wide.insert(address, value);
+        }
+    }
+
+    fn wide_cells(&self) -> Vec<(usize, i128)> {
+        self.wide
+            .iter()
+            .map(|(&address, &value)| (address, value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distant_write_stays_small() {
+        let mut memory = PagedMemory::from_program(&[1101, 1, 1, 1_000_000_000_000_000, 99]);
+        memory.set(1_000_000_000_000_000, 2);
+
+        assert_eq!(memory.get(1_000_000_000_000_000), 2);
+        assert_eq!(memory.get(1_000_000_000_000_001), 0);
+        assert_eq!(memory.get(999_999_999_999_999), 0);
+        assert_eq!(memory.len(), 1_000_000_000_000_001);
+        let pages: Vec<usize> = memory.pages.iter().map(|(index, _)| index).collect();
+        assert_eq!(pages, vec![0, 1_000_000_000_000_000 >> PAGE_BITS]);
+    }
+
+    #[test]
+    fn runs_follow_written_cells() {
+        let mut memory = PagedMemory::from_program(&[1, 2, 3]);
+        memory.set(PAGE_SIZE + 5, 4);
+        memory.set(1 << 40, 5);
+        let runs = memory.runs();
+        assert_eq!(
+            runs.iter()
+                .map(|(address, run)| (*address, run.len()))
+                .collect::<Vec<_>>(),
+            vec![(0, 2 * PAGE_SIZE), (1 << 40, 1)]
+        );
+        assert_eq!(runs[0].1[PAGE_SIZE + 5], 4);
+        assert_eq!(runs[1].1.last(), Some(&5));
+
+        let mut memory = SparseMemory::from_program(&[1, 2, 3]);
+        memory.set(5, 4);
+        memory.set(6, 0);
+        memory.set(1 << 40, 5);
+        assert_eq!(
+            memory.runs(),
+            vec![(0, vec![1, 2, 3]), (5, vec![4, 0]), (1 << 40, vec![5])]
+        );
+    }
+
+    #[test]
+    fn wide_cells() {
+        let wide = i128::from(i64::MAX) + 1;
+        let mut memory = PagedMemory::from_program(&[1, 2, 3]);
+        memory.set_wide(1, wide);
+        memory.set_wide(2, -4);
+        memory.set_wide(3, -wide * 2);
+        assert_eq!(memory.get_wide(1), wide);
+        assert_eq!(memory.get(1), i64::MIN);
+        assert_eq!(memory.get_wide(2), -4);
+        assert_eq!(memory.wide_cells(), vec![(1, wide), (3, -wide * 2)]);
+
+        memory.set(1, 5);
+        assert_eq!(memory.get_wide(1), 5);
+        memory.truncate(3);
+        assert_eq!(memory.wide_cells(), vec![]);
+
+        let mut memory = SparseMemory::from_program(&[1, 2, 3]);
+        memory.set_wide(0, wide);
+        assert_eq!(memory.get_wide(0), wide);
+        memory.set(0, 1);
+        assert_eq!(memory.wide_cells(), vec![]);
+    }
+
+    #[test]
+    fn clones_share_pages_until_written() {
+        let mut memory = PagedMemory::from_program(&[1, 2, 3]);
+        memory.set(1 << 40, 7);
+        let mut clone = memory.clone();
+        clone.set(0, 9);
+        clone.set(1 << 40, 8);
+
+        assert_eq!(memory.get(0), 1);
+        assert_eq!(memory.get(1 << 40), 7);
+        assert_eq!(clone.get(0), 9);
+        assert_eq!(clone.get(1 << 40), 8);
+    }
+
+    #[test]
+    fn truncate_forgets_cells() {
+        let mut memory = PagedMemory::from_program(&[1, 2, 3]);
+        memory.set(2000, 5);
+        memory.set(1 << 40, 6);
+        let clone = memory.clone();
+
+        memory.truncate(2);
+        assert_eq!(memory.len(), 2);
+        assert_eq!(memory.get(1), 2);
+        assert_eq!(memory.get(2), 0);
+        assert_eq!(memory.get(2000), 0);
+        assert_eq!(memory.get(1 << 40), 0);
+        assert_eq!(clone.get(2), 3);
+        assert_eq!(clone.get(1 << 40), 6);
+
+        let mut memory = SparseMemory::from_program(&[1, 2, 3]);
+        memory.set(2000, 5);
+        memory.truncate(2);
+        assert_eq!(memory.len(), 2);
+        assert_eq!(memory.get(2), 0);
+        assert_eq!(memory.get(2000), 0);
+    }
+}
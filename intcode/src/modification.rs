@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+
+// A write to a cell which has already been executed as part of an
+// instruction: the opcode or one of its parameters. The instruction count
+// is the number of instructions executed before the one which wrote.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CodeWrite {
+    pub ip: usize,
+    pub address: usize,
+    pub old: i64,
+    pub new: i64,
+    pub instructions: u64,
+}
+
+// Remembers which cells have been executed, and the writes which have
+// landed on them since
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CodeTracker {
+    executed: HashSet<usize>,
+    writes: Vec<CodeWrite>,
+}
+
+impl CodeTracker {
+    // Records an instruction of the given length executing at ip, and the
+    // write it makes if any. An instruction which overwrites itself counts
+    // as modifying code.
+    pub(crate) fn record(&mut self, ip: usize, length: usize, write: Option<CodeWrite>) {
+        self.executed.extend(ip..ip + length);
+        if let Some(write) = write {
+            if self.executed.contains(&write.address) {
+                self.writes.push(write);
+            }
+        }
+    }
+
+    // Forgets the write made by the instruction with the given count, when
+    // history undoes it
+    pub(crate) fn undo(&mut self, instructions: u64) {
+        if let Some(write) = self.writes.last() {
+            if write.instructions == instructions {
+                self.writes.pop();
+            }
+        }
+    }
+
+    pub(crate) fn writes(&self) -> &[CodeWrite] {
+        &self.writes
+    }
+
+    pub(crate) fn take_writes(&mut self) -> Vec<CodeWrite> {
+        std::mem::take(&mut self.writes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::Computer;
+    use crate::trace::NoTrace;
+    use std::collections::VecDeque;
+
+    fn tracked(program: &[i64]) -> Computer<VecDeque<i64>, Vec<i64>> {
+        let mut computer = Computer::new(program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_code_tracking(true);
+        computer
+    }
+
+    #[test]
+    fn writes_to_executed_cells() {
+        // The first write lands past the code, the second on the first
+        // instruction's opcode
+        let mut computer = tracked(&[1101, 1, 1, 9, 1101, 2, 2, 0, 99]);
+        computer.run().unwrap();
+        assert_eq!(
+            computer.code_writes(),
+            &[CodeWrite {
+                ip: 4,
+                address: 0,
+                old: 1101,
+                new: 4,
+                instructions: 1,
+            }]
+        );
+
+        assert_eq!(computer.take_code_writes().len(), 1);
+        assert_eq!(computer.code_writes(), &[]);
+        assert_eq!(computer.take_code_writes(), vec![]);
+    }
+
+    #[test]
+    fn instructions_which_overwrite_themselves() {
+        let mut computer = tracked(&[1101, 5, 6, 3, 99]);
+        computer.run().unwrap();
+        assert_eq!(
+            computer.take_code_writes(),
+            vec![CodeWrite {
+                ip: 0,
+                address: 3,
+                old: 3,
+                new: 11,
+                instructions: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn patches_from_the_host() {
+        // Like day 13 inserting quarters, or day 02 setting the noun and
+        // verb, before the machine runs
+        let mut computer = tracked(&[1, 0, 0, 7, 4, 7, 99, 0]);
+        computer.set_value(0, 2);
+        computer.set_value(1, 5);
+        computer.set_value(2, 6);
+        computer.run().unwrap();
+        assert_eq!(computer.output(), &[7 * 99]);
+
+        // Nor is a patch to code which has already run
+        computer.set_value(4, 104);
+        assert_eq!(computer.code_writes(), &[]);
+    }
+
+    #[test]
+    fn undone_writes_are_forgotten() {
+        let mut computer = tracked(&[1101, 5, 6, 3, 99]);
+        computer.set_history(true);
+        computer.step().unwrap();
+        assert_eq!(computer.code_writes().len(), 1);
+
+        assert!(computer.step_back());
+        assert_eq!(computer.code_writes(), &[]);
+        assert_eq!(computer.get_value(3), 3);
+
+        // Running it again records the write again
+        computer.run().unwrap();
+        assert_eq!(computer.code_writes().len(), 1);
+
+        // As does a fresh start, from nothing
+        computer.set_code_tracking(true);
+        assert_eq!(computer.code_writes(), &[]);
+    }
+}
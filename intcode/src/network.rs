@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+
+use crate::computer::{Computer, StepResult};
+use crate::error::ScheduleError;
+use crate::memory::{Memory, PagedMemory};
+use crate::scheduler::Machine;
+use crate::trace::NoTrace;
+
+// The address of the NAT, which keeps the last packet sent to it and sends
+// it on to address 0 whenever the network goes idle
+pub const NAT: usize = 255;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Packet {
+    pub from: usize,
+    pub to: usize,
+    pub x: i64,
+    pub y: i64,
+}
+
+type PacketHook = Box<dyn FnMut(&Packet)>;
+
+// Machines which talk in packets: each outputs a destination address, X
+// and Y, which are queued as input for the destination. A machine which
+// reads input while its queue is empty gets -1. The network is idle once
+// a full round passes in which no packet is sent and every machine has
+// found its queue empty since its last packet arrived.
+pub struct Network<M = PagedMemory> {
+    machines: Vec<Machine<M>>,
+    partial: Vec<Vec<i64>>,
+    idle: Vec<bool>,
+    halted: Vec<bool>,
+    nat: Option<Packet>,
+    hooks: Vec<PacketHook>,
+}
+
+impl Network {
+    // Boots count copies of a program, each given its address as its first
+    // input
+    pub fn new(program: &[i64], count: usize) -> Network {
+        let machines = (0..count)
+            .map(|address| {
+                Computer::new(
+                    program,
+                    VecDeque::from([address as i64]),
+                    Vec::new(),
+                    NoTrace,
+                )
+            })
+            .collect();
+
+        Network::with_machines(machines)
+    }
+}
+
+impl<M: Memory> Network<M> {
+    // Joins machines which have already been booted. Each machine's address
+    // is its index.
+    pub fn with_machines(machines: Vec<Machine<M>>) -> Network<M> {
+        let count = machines.len();
+
+        Network {
+            machines,
+            partial: vec![Vec::new(); count],
+            idle: vec![false; count],
+            halted: vec![false; count],
+            nat: None,
+            hooks: Vec::new(),
+        }
+    }
+
+    // Calls hook with every packet delivered from here on, including
+    // packets to and from the NAT
+    pub fn on_packet<F: FnMut(&Packet) + 'static>(&mut self, hook: F) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn machine(&self, address: usize) -> &Machine<M> {
+        &self.machines[address]
+    }
+
+    // The last packet sent to the NAT
+    pub fn nat(&self) -> Option<Packet> {
+        self.nat
+    }
+
+    // Runs the network until stop returns true for a packet delivered, and
+    // returns that packet, or None if every machine halts first. A network
+    // which goes idle with nothing for the NAT to send, or with machine 0
+    // halted, is deadlocked.
+    pub fn run_until<F: FnMut(&Packet) -> bool>(
+        &mut self,
+        mut stop: F,
+    ) -> Result<Option<Packet>, ScheduleError> {
+        loop {
+            let mut sent = false;
+
+            for index in 0..self.machines.len() {
+                if self.halted[index] {
+                    continue;
+                }
+
+                loop {
+                    let step_result = self.machines[index]
+                        .run_until_io()
+                        .map_err(|error| ScheduleError::Machine { index, error })?;
+                    match step_result {
+                        StepResult::Output(value) => {
+                            self.partial[index].push(value);
+                            if self.partial[index].len() < 3 {
+                                continue;
+                            }
+
+                            let (address, x, y) = (
+                                self.partial[index][0],
+                                self.partial[index][1],
+                                self.partial[index][2],
+                            );
+                            self.partial[index].clear();
+                            let to = match usize::try_from(address) {
+                                Ok(to) if to < self.machines.len() || to == NAT => to,
+                                _ => return Err(ScheduleError::UnknownAddress { index, address }),
+                            };
+
+                            let packet = Packet {
+                                from: index,
+                                to,
+                                x,
+                                y,
+                            };
+                            sent = true;
+                            if self.deliver(packet, &mut stop) {
+                                return Ok(Some(packet));
+                            }
+                        }
+                        StepResult::NeedsInput => {
+                            self.machines[index].input_mut().push_back(-1);
+                            self.idle[index] = true;
+                            break;
+                        }
+                        StepResult::Halted => {
+                            self.halted[index] = true;
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if self.halted.iter().all(|&halted| halted) {
+                return Ok(None);
+            }
+            let idle = (0..self.machines.len()).all(|index| self.halted[index] || self.idle[index]);
+            if !sent && idle {
+                let packet = match self.nat {
+                    Some(packet) if !self.halted[0] => Packet {
+                        from: NAT,
+                        to: 0,
+                        ..packet
+                    },
+                    _ => {
+                        let blocked = (0..self.machines.len())
+                            .filter(|&index| !self.halted[index])
+                            .collect();
+                        return Err(ScheduleError::Deadlock { blocked });
+                    }
+                };
+                if self.deliver(packet, &mut stop) {
+                    return Ok(Some(packet));
+                }
+            }
+        }
+    }
+
+    // Queues a packet for its destination, or hands it to the NAT, and
+    // returns whether the network should stop
+    fn deliver<F: FnMut(&Packet) -> bool>(&mut self, packet: Packet, stop: &mut F) -> bool {
+        if packet.to == NAT {
+            self.nat = Some(packet);
+        } else {
+            let input = self.machines[packet.to].input_mut();
+            input.push_back(packet.x);
+            input.push_back(packet.y);
+            self.idle[packet.to] = false;
+        }
+
+        for hook in self.hooks.iter_mut() {
+            hook(&packet);
+        }
+
+        stop(&packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Machine 0 answers each packet by sending the NAT the same X, with Y
+    // one higher until it reaches 3. Every other machine sends the NAT one
+    // packet when it boots. All of them read -1 while they wait.
+    fn counter() -> Vec<i64> {
+        assemble(
+            "
+                    in   address
+                    jf   address #serve
+                    out  #255
+                    out  #7
+                    out  #1
+            serve:  in   x
+                    eq   x #-1 empty
+                    jt   empty #serve
+                    in   y
+                    lt   y #3 less
+                    add  y less y
+                    out  #255
+                    out  x
+                    out  y
+                    jt   #1 #serve
+            address: data 0
+            x:      data 0
+            y:      data 0
+            empty:  data 0
+            less:   data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    fn packet(from: usize, to: usize, x: i64, y: i64) -> Packet {
+        Packet { from, to, x, y }
+    }
+
+    #[test]
+    fn nat_resends_when_idle() {
+        let mut network = Network::new(&counter(), 2);
+        let packets = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&packets);
+        network.on_packet(move |packet| log.borrow_mut().push(*packet));
+
+        // Stops at the first Y the NAT sends twice in a row
+        let mut last = None;
+        let stopped = network
+            .run_until(|packet| {
+                if packet.from != NAT {
+                    return false;
+                }
+                let repeated = last == Some(packet.y);
+                last = Some(packet.y);
+                repeated
+            })
+            .unwrap();
+
+        assert_eq!(stopped, Some(packet(NAT, 0, 7, 3)));
+        assert_eq!(
+            *packets.borrow(),
+            vec![
+                packet(1, NAT, 7, 1),
+                packet(NAT, 0, 7, 1),
+                packet(0, NAT, 7, 2),
+                packet(NAT, 0, 7, 2),
+                packet(0, NAT, 7, 3),
+                packet(NAT, 0, 7, 3),
+                packet(0, NAT, 7, 3),
+                packet(NAT, 0, 7, 3),
+            ]
+        );
+        assert_eq!(network.nat(), Some(packet(0, NAT, 7, 3)));
+    }
+
+    #[test]
+    fn empty_queues_read_minus_one() {
+        // Sending at boot keeps the network busy for a second round
+        let program = assemble(
+            "
+                    in   address
+                    out  #255
+                    out  #0
+                    out  #0
+                    in   x
+                    out  #255
+                    out  x
+                    out  address
+                    hlt
+            address: data 0
+            x:      data 0
+            ",
+        )
+        .unwrap();
+        let mut network = Network::new(&program, 2);
+
+        assert_eq!(
+            network.run_until(|packet| packet.from == 1 && packet.x == -1),
+            Ok(Some(packet(1, NAT, -1, 1)))
+        );
+        assert_eq!(network.nat(), Some(packet(1, NAT, -1, 1)));
+    }
+
+    #[test]
+    fn idle_with_nothing_for_the_nat() {
+        // Machine 1 never sends, so once both wait the NAT has nothing
+        let mut network = Network::new(&counter(), 1);
+        assert_eq!(
+            network.run_until(|_| false),
+            Err(ScheduleError::Deadlock { blocked: vec![0] })
+        );
+    }
+
+    #[test]
+    fn halted_machines() {
+        let program = assemble("in address\nhlt\naddress: data 0").unwrap();
+        let mut network = Network::new(&program, 3);
+        assert_eq!(network.run_until(|_| true), Ok(None));
+
+        // The NAT has a packet but machine 0 cannot take it
+        let program = assemble(
+            "
+                    in   address
+                    jf   address #done
+                    out  #255
+                    out  #1
+                    out  #2
+            wait:   in   address
+                    jt   #1 #wait
+            done:   hlt
+            address: data 0
+            ",
+        )
+        .unwrap();
+        let mut network = Network::new(&program, 3);
+        assert_eq!(
+            network.run_until(|_| false),
+            Err(ScheduleError::Deadlock {
+                blocked: vec![1, 2]
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_address() {
+        let program = assemble(
+            "
+                    in   address
+                    out  #7
+                    out  #1
+                    out  #2
+                    hlt
+            address: data 0
+            ",
+        )
+        .unwrap();
+        let mut network = Network::new(&program, 2);
+        assert_eq!(
+            network.run_until(|_| false),
+            Err(ScheduleError::UnknownAddress {
+                index: 0,
+                address: 7
+            })
+        );
+    }
+}
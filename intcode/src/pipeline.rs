@@ -0,0 +1,365 @@
+use std::collections::VecDeque;
+use std::thread;
+
+use crate::computer::Computer;
+use crate::error::ScheduleError;
+use crate::scheduler::{Scheduler, Traffic};
+use crate::trace::NoTrace;
+
+// How the nodes of a pipeline are wired. In a chain each node feeds the
+// next and the last node's output leaves the pipeline; a ring also feeds
+// the last node's output back to the first.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Topology {
+    Chain,
+    Ring,
+}
+
+// The result of running a pipeline: the last value the last node output,
+// and every value output by every node, in the order they were output
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PipelineOutput {
+    pub output: Option<i64>,
+    pub traffic: Vec<Traffic>,
+}
+
+// The best phase settings a search found, and the output they give
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BestPhases {
+    pub phases: Vec<i64>,
+    pub output: i64,
+}
+
+// Copies of one program wired together, each node starting with its own
+// initial inputs, such as a phase setting. The pipeline's input follows
+// the first node's initial inputs.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    program: Vec<i64>,
+    topology: Topology,
+    nodes: Vec<Vec<i64>>,
+    input: Vec<i64>,
+}
+
+impl Pipeline {
+    pub fn new(program: &[i64], topology: Topology) -> Pipeline {
+        Pipeline {
+            program: program.to_vec(),
+            topology,
+            nodes: Vec::new(),
+            input: Vec::new(),
+        }
+    }
+
+    // Adds a node which starts with the given inputs
+    pub fn node(mut self, initial_inputs: &[i64]) -> Pipeline {
+        self.nodes.push(initial_inputs.to_vec());
+        self
+    }
+
+    // Adds a node for each phase setting, starting with that setting as its
+    // only initial input
+    pub fn phases(mut self, phases: &[i64]) -> Pipeline {
+        self.nodes.extend(phases.iter().map(|&phase| vec![phase]));
+        self
+    }
+
+    pub fn input(mut self, values: &[i64]) -> Pipeline {
+        self.input.extend_from_slice(values);
+        self
+    }
+
+    pub fn run(&self) -> Result<PipelineOutput, ScheduleError> {
+        let mut scheduler = Scheduler::new();
+        scheduler.set_recording(true);
+
+        for (index, initial_inputs) in self.nodes.iter().enumerate() {
+            let mut input = VecDeque::from(initial_inputs.clone());
+            if index == 0 {
+                input.extend(self.input.iter().copied());
+            }
+            scheduler.add(Computer::new(&self.program, input, Vec::new(), NoTrace));
+        }
+        for index in 1..self.nodes.len() {
+            scheduler.connect(index - 1, index);
+        }
+        if self.topology == Topology::Ring && !self.nodes.is_empty() {
+            scheduler.connect(self.nodes.len() - 1, 0);
+        }
+
+        scheduler.run()?;
+
+        let output = match self.nodes.len() {
+            0 => None,
+            count => scheduler.machine(count - 1).output().last().copied(),
+        };
+        Ok(PipelineOutput {
+            output,
+            traffic: scheduler.into_traffic(),
+        })
+    }
+
+    // Tries every ordering of count distinct phase settings drawn from
+    // phases, as the nodes of this pipeline in place of any it has, and
+    // returns the ordering which gives the highest output. Orderings are
+    // shared out between threads, but ties and errors go to the ordering
+    // which comes first, so the result is always the same. Returns None if
+    // no ordering gives any output.
+    pub fn search(
+        &self,
+        phases: &[i64],
+        count: usize,
+    ) -> Result<Option<BestPhases>, ScheduleError> {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        self.search_threads(phases, count, threads)
+    }
+
+    fn search_threads(
+        &self,
+        phases: &[i64],
+        count: usize,
+        threads: usize,
+    ) -> Result<Option<BestPhases>, ScheduleError> {
+        let orderings = permutations(phases, count);
+        let chunk_size = orderings.len().div_ceil(threads).max(1);
+
+        let results: Vec<Result<Option<BestPhases>, ScheduleError>> = thread::scope(|scope| {
+            let handles: Vec<_> = orderings
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || self.search_chunk(chunk)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        let mut best: Option<BestPhases> = None;
+        for result in results {
+            if let Some(candidate) = result? {
+                if best
+                    .as_ref()
+                    .is_none_or(|best| candidate.output > best.output)
+                {
+                    best = Some(candidate);
+                }
+            }
+        }
+
+        Ok(best)
+    }
+
+    fn search_chunk(&self, orderings: &[Vec<i64>]) -> Result<Option<BestPhases>, ScheduleError> {
+        let mut best: Option<BestPhases> = None;
+
+        for phases in orderings {
+            let mut pipeline = self.clone();
+            pipeline.nodes.clear();
+            let output = match pipeline.phases(phases).run()?.output {
+                Some(output) => output,
+                None => continue,
+            };
+            if best.as_ref().is_none_or(|best| output > best.output) {
+                best = Some(BestPhases {
+                    phases: phases.clone(),
+                    output,
+                });
+            }
+        }
+
+        Ok(best)
+    }
+}
+
+// Every ordering of count distinct values drawn from values, in
+// lexicographic order of position
+fn permutations(values: &[i64], count: usize) -> Vec<Vec<i64>> {
+    fn extend(
+        values: &[i64],
+        count: usize,
+        used: &mut [bool],
+        current: &mut Vec<i64>,
+        result: &mut Vec<Vec<i64>>,
+    ) {
+        if current.len() == count {
+            result.push(current.clone());
+            return;
+        }
+        for index in 0..values.len() {
+            if used[index] {
+                continue;
+            }
+            used[index] = true;
+            current.push(values[index]);
+            extend(values, count, used, current, result);
+            current.pop();
+            used[index] = false;
+        }
+    }
+
+    let mut result = Vec::new();
+    if count <= values.len() {
+        extend(
+            values,
+            count,
+            &mut vec![false; values.len()],
+            &mut Vec::new(),
+            &mut result,
+        );
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::error::IntcodeError;
+
+    // Adds its initial input to the value it is sent
+    fn adder() -> Vec<i64> {
+        assemble(
+            "
+                    in   a
+                    in   b
+                    add  a b a
+                    out  a
+                    hlt
+            a:      data 0
+            b:      data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn chain_and_ring() {
+        let traffic = |from, to, value| Traffic { from, to, value };
+
+        let chain = Pipeline::new(&adder(), Topology::Chain)
+            .node(&[1])
+            .node(&[10])
+            .input(&[100]);
+        assert_eq!(
+            chain.run(),
+            Ok(PipelineOutput {
+                output: Some(111),
+                traffic: vec![traffic(0, Some(1), 101), traffic(1, None, 111)],
+            })
+        );
+
+        // The last value goes back to the first node, which has halted
+        let ring = Pipeline::new(&adder(), Topology::Ring)
+            .node(&[1])
+            .node(&[10])
+            .input(&[100]);
+        assert_eq!(
+            ring.run(),
+            Ok(PipelineOutput {
+                output: Some(111),
+                traffic: vec![traffic(0, Some(1), 101), traffic(1, Some(0), 111)],
+            })
+        );
+
+        let empty = Pipeline::new(&adder(), Topology::Ring).input(&[100]);
+        assert_eq!(empty.run().unwrap().output, None);
+    }
+
+    #[test]
+    fn day_07_examples() {
+        let chain = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(
+            Pipeline::new(&chain, Topology::Chain)
+                .input(&[0])
+                .search(&[0, 1, 2, 3, 4], 5),
+            Ok(Some(BestPhases {
+                phases: vec![4, 3, 2, 1, 0],
+                output: 43210,
+            }))
+        );
+
+        let ring = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        assert_eq!(
+            Pipeline::new(&ring, Topology::Ring)
+                .input(&[0])
+                .search(&[5, 6, 7, 8, 9], 5),
+            Ok(Some(BestPhases {
+                phases: vec![9, 8, 7, 6, 5],
+                output: 139629729,
+            }))
+        );
+    }
+
+    #[test]
+    fn ties_go_to_the_first_ordering() {
+        // Each node adds one for a phase above 1, so every ordering using
+        // both 2 and 3 ties, the first of them being the fourth ordering
+        let program = assemble(
+            "
+                    in   phase
+                    in   value
+                    lt   #1 phase above
+                    add  value above value
+                    out  value
+                    hlt
+            phase:  data 0
+            value:  data 0
+            above:  data 0
+            ",
+        )
+        .unwrap();
+        let pipeline = Pipeline::new(&program, Topology::Chain).input(&[0]);
+
+        for threads in 1..=8 {
+            assert_eq!(
+                pipeline.search_threads(&[0, 1, 2, 3], 3, threads),
+                Ok(Some(BestPhases {
+                    phases: vec![0, 2, 3],
+                    output: 2,
+                }))
+            );
+        }
+    }
+
+    #[test]
+    fn errors_go_to_the_first_ordering() {
+        // A node given phase 3 jumps to a negative address. The first
+        // ordering using 3 is 0, 1, 3, where it is the third node.
+        let program = assemble(
+            "
+                    in   phase
+                    in   value
+                    eq   phase #3 bad
+                    jt   bad #-1
+                    out  value
+                    hlt
+            phase:  data 0
+            value:  data 0
+            bad:    data 0
+            ",
+        )
+        .unwrap();
+        let pipeline = Pipeline::new(&program, Topology::Chain).input(&[0]);
+
+        for threads in 1..=8 {
+            assert_eq!(
+                pipeline.search_threads(&[0, 1, 2, 3], 3, threads),
+                Err(ScheduleError::Machine {
+                    index: 2,
+                    error: IntcodeError::NegativeJump {
+                        ip: 8,
+                        instruction: 1005,
+                        offset: 2,
+                        target: -1,
+                    },
+                })
+            );
+        }
+    }
+}
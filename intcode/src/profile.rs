@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::instruction::{Mode, Opcode, Parameter};
+
+// How many entries each section of the report lists
+const REPORT_LENGTH: usize = 10;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub end: usize,
+    pub instructions: usize,
+    pub executions: u64,
+}
+
+// A taken jump back to an earlier (or the same) address, with the range of
+// addresses it loops over
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HotLoop {
+    pub start: usize,
+    pub end: usize,
+    pub iterations: u64,
+}
+
+// Execution counts gathered while a Computer runs with profiling on. Reads
+// count position and relative mode operands which are read, not
+// instruction fetches.
+#[derive(Debug, Default, Clone)]
+pub struct Profile {
+    pub instructions: u64,
+    pub opcodes: HashMap<Opcode, u64>,
+    pub ips: HashMap<usize, u64>,
+    pub reads: HashMap<usize, u64>,
+    pub writes: HashMap<usize, u64>,
+    pub jumps: HashMap<(usize, usize), u64>,
+    entry: Option<usize>,
+    ip_opcodes: HashMap<usize, Opcode>,
+    // A halted machine which is run again executes its halt again, which
+    // is neither counted nor reported a second time
+    halted: bool,
+}
+
+impl Profile {
+    // Counts one executed instruction. Returns true when the program halts
+    // for the first time, which is when the report is due.
+    pub(crate) fn record(
+        &mut self,
+        ip: usize,
+        opcode: Opcode,
+        parameters: &[Parameter],
+        new_ip: usize,
+    ) -> bool {
+        if opcode == Opcode::Halt {
+            if self.halted {
+                return false;
+            }
+            self.halted = true;
+        }
+
+        self.instructions += 1;
+        *self.opcodes.entry(opcode).or_insert(0) += 1;
+        *self.ips.entry(ip).or_insert(0) += 1;
+        self.ip_opcodes.insert(ip, opcode);
+        if self.entry.is_none() {
+            self.entry = Some(ip);
+        }
+
+        for (index, parameter) in parameters.iter().enumerate() {
+            if parameter.mode == Mode::Immediate {
+                continue;
+            }
+            if opcode.write_offset() == Some(index + 1) {
+                *self.writes.entry(parameter.address).or_insert(0) += 1;
+            } else {
+                *self.reads.entry(parameter.address).or_insert(0) += 1;
+            }
+        }
+
+        if opcode != Opcode::Halt && new_ip != ip + 1 + parameters.len() {
+            *self.jumps.entry((ip, new_ip)).or_insert(0) += 1;
+        }
+
+        opcode == Opcode::Halt
+    }
+
+    // Splits the executed instructions into basic blocks, hottest first. A
+    // block starts at the entry point, at a jump target, after a jump, or
+    // where execution was not contiguous, and ends after a jump or halt.
+    pub fn basic_blocks(&self) -> Vec<BasicBlock> {
+        let mut leaders: HashSet<usize> = self.entry.into_iter().collect();
+        for (&ip, &opcode) in self.ip_opcodes.iter() {
+            if let Opcode::JumpIfTrue | Opcode::JumpIfFalse = opcode {
+                leaders.insert(ip + 1 + opcode.parameter_count());
+            }
+        }
+        for &(_, target) in self.jumps.keys() {
+            leaders.insert(target);
+        }
+
+        let ips: BTreeMap<usize, Opcode> = self.ip_opcodes.iter().map(|(&k, &v)| (k, v)).collect();
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut next_ip = None;
+        for (&ip, &opcode) in ips.iter() {
+            match blocks.last_mut() {
+                Some(block) if next_ip == Some(ip) && !leaders.contains(&ip) => {
+                    block.end = ip;
+                    block.instructions += 1;
+                }
+                _ => blocks.push(BasicBlock {
+                    start: ip,
+                    end: ip,
+                    instructions: 1,
+                    executions: self.ips[&ip],
+                }),
+            }
+
+            next_ip = match opcode {
+                Opcode::JumpIfTrue | Opcode::JumpIfFalse | Opcode::Halt => None,
+                _ => Some(ip + 1 + opcode.parameter_count()),
+            };
+        }
+
+        blocks.sort_by(|a, b| b.executions.cmp(&a.executions).then(a.start.cmp(&b.start)));
+        blocks
+    }
+
+    pub fn hot_loops(&self) -> Vec<HotLoop> {
+        let mut loops: Vec<HotLoop> = self
+            .jumps
+            .iter()
+            .filter(|((ip, target), _)| target <= ip)
+            .map(|(&(ip, target), &iterations)| HotLoop {
+                start: target,
+                end: ip,
+                iterations,
+            })
+            .collect();
+
+        loops.sort_by(|a, b| b.iterations.cmp(&a.iterations).then(a.start.cmp(&b.start)));
+        loops
+    }
+
+    pub fn report(&self) -> String {
+        let mut result = String::new();
+
+        result.push_str(&format!("Total instructions: {}\n", self.instructions));
+
+        result.push_str("\nOpcodes:\n");
+        let mut opcodes: Vec<(&Opcode, &u64)> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.code().cmp(&b.0.code())));
+        for (opcode, count) in opcodes {
+            result.push_str(&format!(
+                "  {:<4} {:>12} {:>6.2}%\n",
+                opcode.mnemonic(),
+                count,
+                100.0 * *count as f64 / self.instructions as f64
+            ));
+        }
+
+        result.push_str("\nHot loops:\n");
+        for hot_loop in self.hot_loops().iter().take(REPORT_LENGTH) {
+            result.push_str(&format!(
+                "  {:>5}..={:<5} {:>12} iterations\n",
+                hot_loop.start, hot_loop.end, hot_loop.iterations
+            ));
+        }
+
+        result.push_str("\nBasic blocks:\n");
+        for block in self.basic_blocks().iter().take(REPORT_LENGTH) {
+            result.push_str(&format!(
+                "  {:>5}..={:<5} {:>12} executions, {} instructions\n",
+                block.start, block.end, block.executions, block.instructions
+            ));
+        }
+
+        result.push_str("\nHot instructions:\n");
+        let mut ips: Vec<(&usize, &u64)> = self.ips.iter().collect();
+        ips.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (ip, count) in ips.into_iter().take(REPORT_LENGTH) {
+            result.push_str(&format!("  ip {:>5} {:>12}\n", ip, count));
+        }
+
+        result.push_str("\nHot memory:\n");
+        let mut addresses: Vec<(usize, u64, u64)> = self
+            .reads
+            .keys()
+            .chain(self.writes.keys())
+            .collect::<HashSet<&usize>>()
+            .into_iter()
+            .map(|address| {
+                (
+                    *address,
+                    *self.reads.get(address).unwrap_or(&0),
+                    *self.writes.get(address).unwrap_or(&0),
+                )
+            })
+            .collect();
+        addresses.sort_by(|a, b| (b.1 + b.2).cmp(&(a.1 + a.2)).then(a.0.cmp(&b.0)));
+        for (address, reads, writes) in addresses.into_iter().take(REPORT_LENGTH) {
+            result.push_str(&format!(
+                "  address {:>5} {:>12} reads {:>12} writes\n",
+                address, reads, writes
+            ));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::computer::Computer;
+    use crate::trace::NoTrace;
+    use std::collections::VecDeque;
+
+    // Counts to three, then jumps over a data cell to halt
+    fn profile() -> Profile {
+        let program = assemble(
+            "
+            loop:   add  i #1 i
+                    lt   i #3 c
+                    jt   c #loop
+                    jf   #0 #end
+                    data 7
+            end:    hlt
+            i:      data 0
+            c:      data 0
+            ",
+        )
+        .unwrap();
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_profiling(true);
+        computer.run().unwrap();
+
+        // Running the halted machine again changes nothing
+        computer.run().unwrap();
+        computer.profile().unwrap().clone()
+    }
+
+    #[test]
+    fn counts() {
+        let profile = profile();
+        assert_eq!(profile.instructions, 11);
+        assert_eq!(profile.opcodes[&Opcode::Add], 3);
+        assert_eq!(profile.opcodes[&Opcode::Halt], 1);
+        assert_eq!(profile.ips[&8], 3);
+        assert_eq!(profile.reads[&16], 6);
+        assert_eq!(profile.writes[&17], 3);
+        assert_eq!(profile.jumps, HashMap::from([((8, 0), 2), ((11, 15), 1)]));
+    }
+
+    #[test]
+    fn basic_blocks() {
+        assert_eq!(
+            profile().basic_blocks(),
+            vec![
+                BasicBlock {
+                    start: 0,
+                    end: 8,
+                    instructions: 3,
+                    executions: 3,
+                },
+                BasicBlock {
+                    start: 11,
+                    end: 11,
+                    instructions: 1,
+                    executions: 1,
+                },
+                BasicBlock {
+                    start: 15,
+                    end: 15,
+                    instructions: 1,
+                    executions: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn hot_loops() {
+        assert_eq!(
+            profile().hot_loops(),
+            vec![HotLoop {
+                start: 0,
+                end: 8,
+                iterations: 2,
+            }]
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use crate::error::ParseError;
+
+// Reads a program written as comma separated integers, as puzzle inputs
+// are. Whitespace around the program and around each integer is ignored.
+pub fn parse_program(text: &str) -> Result<Vec<i64>, ParseError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    text.split(',')
+        .enumerate()
+        .map(|(index, word)| {
+            word.trim()
+                .parse::<i64>()
+                .map_err(|_| ParseError::InvalidInteger {
+                    index,
+                    text: word.trim().to_string(),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_puzzle_input() {
+        assert_eq!(parse_program("1,0,0,3,99\n"), Ok(vec![1, 0, 0, 3, 99]));
+        assert_eq!(parse_program(" 104, -1 ,99"), Ok(vec![104, -1, 99]));
+        assert_eq!(parse_program("\n"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn reports_invalid_integer() {
+        assert_eq!(
+            parse_program("1,x,99"),
+            Err(ParseError::InvalidInteger {
+                index: 1,
+                text: String::from("x")
+            })
+        );
+        assert_eq!(
+            parse_program("1,,99"),
+            Err(ParseError::InvalidInteger {
+                index: 1,
+                text: String::new()
+            })
+        );
+    }
+}
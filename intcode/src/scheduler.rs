@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+
+use crate::computer::{Computer, StepResult};
+use crate::error::ScheduleError;
+use crate::memory::{Memory, PagedMemory};
+
+pub type Machine<M> = Computer<VecDeque<i64>, Vec<i64>, M>;
+
+// A value one machine output, and the machine it was sent to, if any
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Traffic {
+    pub from: usize,
+    pub to: Option<usize>,
+    pub value: i64,
+}
+
+// Runs many machines on the calling thread. Each machine runs in turn, in
+// the order they were added, until it waits for input which has not
+// arrived yet or halts. A value a machine outputs is kept in its own
+// output, and is also queued as input for the machine it is connected to,
+// if any.
+pub struct Scheduler<M = PagedMemory> {
+    machines: Vec<Machine<M>>,
+    routes: Vec<Option<usize>>,
+    halted: Vec<bool>,
+    traffic: Option<Vec<Traffic>>,
+}
+
+impl<M: Memory> Default for Scheduler<M> {
+    fn default() -> Scheduler<M> {
+        Scheduler {
+            machines: Vec::new(),
+            routes: Vec::new(),
+            halted: Vec::new(),
+            traffic: None,
+        }
+    }
+}
+
+impl<M: Memory> Scheduler<M> {
+    pub fn new() -> Scheduler<M> {
+        Scheduler::default()
+    }
+
+    // Adds a machine and returns its index
+    pub fn add(&mut self, machine: Machine<M>) -> usize {
+        self.machines.push(machine);
+        self.routes.push(None);
+        self.halted.push(false);
+
+        self.machines.len() - 1
+    }
+
+    // Sends the output of one machine to the input of another, replacing
+    // any connection it had
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.routes[from] = Some(to);
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    pub fn machine(&self, index: usize) -> &Machine<M> {
+        &self.machines[index]
+    }
+
+    pub fn machine_mut(&mut self, index: usize) -> &mut Machine<M> {
+        &mut self.machines[index]
+    }
+
+    pub fn halted(&self, index: usize) -> bool {
+        self.halted[index]
+    }
+
+    // With recording on, every value output from here on is kept in order
+    // of output. Turning it off discards what was kept.
+    pub fn set_recording(&mut self, enabled: bool) {
+        self.traffic = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    // The values recorded so far; empty if recording is off
+    pub fn traffic(&self) -> &[Traffic] {
+        self.traffic.as_deref().unwrap_or(&[])
+    }
+
+    // Runs every machine until all of them have halted. Values sent to a
+    // machine which has halted are left in its input. If a full round
+    // passes in which no machine can run, the machines still running are
+    // deadlocked waiting for input.
+    pub fn run(&mut self) -> Result<(), ScheduleError> {
+        while !self.halted.iter().all(|&halted| halted) {
+            if !self.round()? {
+                let blocked = (0..self.machines.len())
+                    .filter(|&index| !self.halted[index])
+                    .collect();
+                return Err(ScheduleError::Deadlock { blocked });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Gives each machine which has not halted one turn. Returns whether any
+    // of them executed an instruction.
+    pub fn round(&mut self) -> Result<bool, ScheduleError> {
+        let mut progress = false;
+
+        for index in 0..self.machines.len() {
+            if self.halted[index] {
+                continue;
+            }
+
+            let instructions = self.machines[index].instructions();
+            loop {
+                let step_result = self.machines[index]
+                    .run_until_io()
+                    .map_err(|error| ScheduleError::Machine { index, error })?;
+                match step_result {
+                    StepResult::Output(value) => {
+                        self.machines[index].output_mut().push(value);
+                        if let Some(to) = self.routes[index] {
+                            self.machines[to].input_mut().push_back(value);
+                        }
+                        if let Some(traffic) = self.traffic.as_mut() {
+                            traffic.push(Traffic {
+                                from: index,
+                                to: self.routes[index],
+                                value,
+                            });
+                        }
+                    }
+                    StepResult::NeedsInput => break,
+                    StepResult::Halted => {
+                        self.halted[index] = true;
+                        break;
+                    }
+                }
+            }
+            progress |= self.machines[index].instructions() > instructions;
+        }
+
+        Ok(progress)
+    }
+
+    pub fn into_machines(self) -> Vec<Machine<M>> {
+        self.machines
+    }
+
+    pub fn into_traffic(self) -> Vec<Traffic> {
+        self.traffic.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::trace::NoTrace;
+
+    fn machine(source: &str, input: &[i64]) -> Machine<PagedMemory> {
+        let program = assemble(source).unwrap();
+        Computer::new(
+            &program,
+            VecDeque::from(input.to_vec()),
+            Vec::new(),
+            NoTrace,
+        )
+    }
+
+    // Doubles each value it reads, and halts after passing on a zero
+    const DOUBLER: &str = "
+        loop:   in   x
+                mul  x #2 x
+                out  x
+                jt   x #loop
+                hlt
+        x:      data 0
+    ";
+
+    #[test]
+    fn connected_machines() {
+        let mut scheduler = Scheduler::new();
+        let first = scheduler.add(machine(DOUBLER, &[1, 2, 0]));
+        let second = scheduler.add(machine(DOUBLER, &[]));
+        scheduler.connect(first, second);
+        scheduler.set_recording(true);
+
+        scheduler.run().unwrap();
+        assert!(scheduler.halted(first) && scheduler.halted(second));
+        assert_eq!(*scheduler.machine(first).output(), vec![2, 4, 0]);
+        assert_eq!(*scheduler.machine(second).output(), vec![4, 8, 0]);
+
+        let traffic = |from, to, value| Traffic { from, to, value };
+        assert_eq!(
+            scheduler.into_traffic(),
+            vec![
+                traffic(0, Some(1), 2),
+                traffic(0, Some(1), 4),
+                traffic(0, Some(1), 0),
+                traffic(1, None, 4),
+                traffic(1, None, 8),
+                traffic(1, None, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn recording_off() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add(machine(DOUBLER, &[1, 0]));
+        scheduler.set_recording(true);
+        scheduler.set_recording(false);
+
+        scheduler.run().unwrap();
+        assert_eq!(scheduler.traffic(), &[]);
+    }
+
+    #[test]
+    fn deadlock() {
+        // Each machine passes one value on and then waits for a second
+        // value, which only the first machine gets
+        let source = "
+                    in   x
+                    out  x
+                    in   x
+                    hlt
+            x:      data 0
+        ";
+        let mut scheduler = Scheduler::new();
+        scheduler.add(machine(source, &[5]));
+        scheduler.add(machine(source, &[]));
+        scheduler.connect(0, 1);
+        scheduler.connect(1, 0);
+
+        assert_eq!(
+            scheduler.run(),
+            Err(ScheduleError::Deadlock { blocked: vec![1] })
+        );
+        assert!(scheduler.halted(0));
+        assert_eq!(*scheduler.machine(1).output(), vec![5]);
+    }
+
+    #[test]
+    fn halted_machines_keep_unread_input() {
+        let mut scheduler = Scheduler::new();
+        let receiver = scheduler.add(machine("in x\nhlt\nx: data 0", &[]));
+        let sender = scheduler.add(machine("out #1\nout #2\nout #3\nhlt", &[]));
+        scheduler.connect(sender, receiver);
+
+        scheduler.run().unwrap();
+        assert_eq!(*scheduler.machine(receiver).input(), VecDeque::from([2, 3]));
+    }
+}
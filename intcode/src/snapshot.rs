@@ -0,0 +1,339 @@
+// The complete state of a Computer, written as a few lines of text:
+//
+//     intcode snapshot 1
+//     ip 14
+//     rb 2000
+//     instructions 1200
+//     input 1,0
+//     output
+//     memory 0 109,1,204,-1,...,0*84,4,0
+//     memory 134217728 5,0*1023
+//     wide 90 -18446744073709551616
+//
+// Each memory line holds a run of consecutive cells after the address of
+// its first cell, and cells outside the runs are zero, so a machine which
+// writes to a few distant addresses keeps a small snapshot. Runs of zeros
+// are written as 0*count. A wide line holds a cell whose value is beyond
+// the range of an i64, as widened arithmetic stores, and the cell's memory
+// line holds its low 64 bits. Pending input and output are only captured
+// from sources and sinks which can report them (see Input::pending and
+// Output::pending); Computer::from_snapshot puts them back.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::SnapshotError;
+
+const HEADER: &str = "intcode snapshot 1";
+
+// The most integers a line may hold, so that a corrupt run of zeros is
+// reported rather than exhausting memory
+const MAX_INTEGERS: usize = 1 << 24;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snapshot {
+    pub ip: usize,
+    pub rb: i64,
+    pub instructions: u64,
+    pub input: Vec<i64>,
+    pub output: Vec<i64>,
+    // Runs of consecutive cells in address order, as Memory::runs gives
+    pub memory: Vec<(usize, Vec<i64>)>,
+    // Cells beyond the range of an i64, as Memory::wide_cells gives
+    pub wide: Vec<(usize, i128)>,
+}
+
+impl Snapshot {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+pub(crate) fn write_integers(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    integers: &[i64],
+) -> fmt::Result {
+    let mut words = Vec::new();
+    let mut index = 0;
+
+    while index < integers.len() {
+        let run = integers[index..]
+            .iter()
+            .take_while(|&&integer| integer == 0)
+            .count();
+        if run > 2 {
+            words.push(format!("0*{}", run));
+            index += run;
+        } else {
+            words.push(integers[index].to_string());
+            index += 1;
+        }
+    }
+
+    if words.is_empty() {
+        writeln!(f, "{}", name)
+    } else {
+        writeln!(f, "{} {}", name, words.join(","))
+    }
+}
+
+pub(crate) fn parse_integers(text: &str) -> Option<Vec<i64>> {
+    let mut integers = Vec::new();
+
+    for word in text.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        if let Some(count) = word.strip_prefix("0*") {
+            let count = count.parse::<usize>().ok()?;
+            if count > MAX_INTEGERS - integers.len() {
+                return None;
+            }
+            integers.extend(std::iter::repeat_n(0, count));
+        } else {
+            if integers.len() == MAX_INTEGERS {
+                return None;
+            }
+            integers.push(word.parse::<i64>().ok()?);
+        }
+    }
+
+    Some(integers)
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "ip {}", self.ip)?;
+        writeln!(f, "rb {}", self.rb)?;
+        writeln!(f, "instructions {}", self.instructions)?;
+        write_integers(f, "input", &self.input)?;
+        write_integers(f, "output", &self.output)?;
+        for (address, run) in &self.memory {
+            // Long runs are split so that each line can be read back
+            for (index, cells) in run.chunks(MAX_INTEGERS).enumerate() {
+                let name = format!("memory {}", address + index * MAX_INTEGERS);
+                write_integers(f, &name, cells)?;
+            }
+        }
+        for (address, value) in &self.wide {
+            writeln!(f, "wide {} {}", address, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = SnapshotError;
+
+    fn from_str(s: &str) -> Result<Snapshot, SnapshotError> {
+        let mut ip = None;
+        let mut rb = None;
+        let mut instructions = None;
+        let mut input = None;
+        let mut output = None;
+        let mut memory: Vec<(usize, Vec<i64>)> = Vec::new();
+        let mut wide = Vec::new();
+
+        for (index, text) in s.lines().enumerate() {
+            let invalid = || SnapshotError::InvalidLine {
+                line: index + 1,
+                text: text.to_string(),
+            };
+
+            if index == 0 {
+                if text.trim() != HEADER {
+                    return Err(invalid());
+                }
+                continue;
+            }
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let (name, rest) = text.split_once(' ').unwrap_or((text, ""));
+            let integers = || parse_integers(rest).ok_or_else(invalid);
+
+            match name {
+                "ip" => ip = Some(rest.trim().parse::<usize>().map_err(|_| invalid())?),
+                "rb" => rb = Some(rest.trim().parse::<i64>().map_err(|_| invalid())?),
+                "instructions" => {
+                    instructions = Some(rest.trim().parse::<u64>().map_err(|_| invalid())?)
+                }
+                "input" => input = Some(integers()?),
+                "output" => output = Some(integers()?),
+                "memory" => {
+                    let (address, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let address = address.parse::<usize>().map_err(|_| invalid())?;
+                    let cells = parse_integers(rest).ok_or_else(invalid)?;
+                    if address.checked_add(cells.len()).is_none() {
+                        return Err(invalid());
+                    }
+
+                    // Runs must not overlap, and a run split over several
+                    // lines is joined up again
+                    match memory.last_mut() {
+                        Some((start, run)) if *start + run.len() == address => run.extend(cells),
+                        Some((start, run)) if *start + run.len() > address => return Err(invalid()),
+                        _ => memory.push((address, cells)),
+                    }
+                }
+                "wide" => {
+                    let (address, value) = rest.split_once(' ').ok_or_else(invalid)?;
+                    wide.push((
+                        address.parse::<usize>().map_err(|_| invalid())?,
+                        value.trim().parse::<i128>().map_err(|_| invalid())?,
+                    ));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Snapshot {
+            ip: ip.ok_or(SnapshotError::MissingField { name: "ip" })?,
+            rb: rb.ok_or(SnapshotError::MissingField { name: "rb" })?,
+            instructions: instructions.ok_or(SnapshotError::MissingField {
+                name: "instructions",
+            })?,
+            input: input.ok_or(SnapshotError::MissingField { name: "input" })?,
+            output: output.ok_or(SnapshotError::MissingField { name: "output" })?,
+            memory,
+            wide,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::computer::{Computer, Limits, StepResult};
+    use crate::io::InputFn;
+    use crate::memory::{Memory, PagedMemory};
+    use crate::trace::NoTrace;
+    use std::collections::VecDeque;
+
+    // Adds pairs of inputs and outputs the sums until it reads a zero
+    fn adder() -> Vec<i64> {
+        assemble(
+            "
+            loop:   in   a
+                    jf   a #done
+                    in   b
+                    add  a b a
+                    out  a
+                    jt   #1 #loop
+            done:   hlt
+            a:      data 0
+            b:      data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut computer =
+            Computer::new(&adder(), VecDeque::from([2, 3, 4, 5]), Vec::new(), NoTrace);
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(5)));
+        computer.output_mut().push(5);
+
+        let snapshot = computer.snapshot();
+        assert_eq!(snapshot.input, vec![4, 5]);
+        assert_eq!(snapshot.output, vec![5]);
+        assert_eq!(snapshot.to_string().parse(), Ok(snapshot));
+    }
+
+    #[test]
+    fn resumes_pending_io_and_count() {
+        let mut computer =
+            Computer::new(&adder(), VecDeque::from([2, 3, 4, 5]), Vec::new(), NoTrace);
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(5)));
+        computer.output_mut().push(5);
+        let snapshot: Snapshot = computer.snapshot().to_string().parse().unwrap();
+
+        let mut resumed: Computer<_, _, PagedMemory> =
+            Computer::from_snapshot(&snapshot, VecDeque::from([0]), Vec::new(), NoTrace);
+        assert_eq!(resumed.instructions(), computer.instructions());
+        resumed.run().unwrap();
+        assert_eq!(resumed.output(), &vec![5, 9]);
+
+        // The count carries on, so a limit covers the whole run
+        let mut resumed: Computer<_, _, PagedMemory> =
+            Computer::from_snapshot(&snapshot, VecDeque::from([0]), Vec::new(), NoTrace);
+        resumed.set_limits(Limits {
+            max_instructions: Some(snapshot.instructions),
+            ..Limits::default()
+        });
+        assert!(resumed.run().is_err());
+    }
+
+    #[test]
+    fn resumes_pending_input_without_a_queue() {
+        let mut computer =
+            Computer::new(&adder(), VecDeque::from([2, 3, 4, 5]), Vec::new(), NoTrace);
+        computer.run_until_io().unwrap();
+        let snapshot = computer.snapshot();
+
+        let mut zero = Some(0);
+        let mut resumed: Computer<_, _, PagedMemory> =
+            Computer::from_snapshot(&snapshot, InputFn(move || zero.take()), Vec::new(), NoTrace);
+        resumed.run().unwrap();
+        assert_eq!(resumed.output(), &vec![9]);
+    }
+
+    #[test]
+    fn corrupt_run_of_zeros() {
+        let text =
+            "intcode snapshot 1\nip 0\nrb 0\ninstructions 0\ninput\noutput\nmemory 0 0*99999999999999";
+        assert_eq!(
+            text.parse::<Snapshot>(),
+            Err(SnapshotError::InvalidLine {
+                line: 7,
+                text: String::from("memory 0 0*99999999999999")
+            })
+        );
+    }
+
+    #[test]
+    fn distant_writes_keep_snapshots_small() {
+        let far = 1_000_000_000_000_000;
+        let program = [1101, 2, 3, 1 << 27, 1101, 4, 5, far, 99];
+        let mut computer = Computer::new(&program, VecDeque::new(), Vec::new(), NoTrace);
+        computer.run().unwrap();
+
+        let text = computer.snapshot().to_string();
+        assert!(text.len() < 200, "snapshot is {} bytes", text.len());
+        let snapshot: Snapshot = text.parse().unwrap();
+        assert_eq!(snapshot, computer.snapshot());
+
+        let resumed: Computer<_, _, PagedMemory> =
+            Computer::from_snapshot(&snapshot, VecDeque::new(), Vec::new(), NoTrace);
+        assert_eq!(resumed.get_value(1 << 27), 5);
+        assert_eq!(resumed.get_value(far as usize), 9);
+        assert_eq!(resumed.get_value(7), far);
+        assert_eq!(resumed.memory().len(), far as usize + 1);
+        assert_eq!(resumed.memory().runs(), computer.memory().runs());
+    }
+
+    #[test]
+    fn overlapping_runs() {
+        let text = "intcode snapshot 1\nip 0\nrb 0\ninstructions 0\ninput\noutput\n\
+                    memory 0 1,2,3\nmemory 2 4";
+        assert_eq!(
+            text.parse::<Snapshot>(),
+            Err(SnapshotError::InvalidLine {
+                line: 8,
+                text: String::from("memory 2 4")
+            })
+        );
+    }
+}
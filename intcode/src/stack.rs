@@ -0,0 +1,138 @@
+use crate::instruction::{Mode, Opcode, Parameter};
+
+// Frames beyond this depth drop the outermost frame, so that a program
+// which jumps like a call but never returns cannot grow the stack forever
+const MAX_DEPTH: usize = 4096;
+
+// A subroutine call inferred from the running program: the subroutine's
+// entry address, the address of the jump which called it, and where it
+// will return to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Frame {
+    pub entry: usize,
+    pub call_site: usize,
+    pub return_address: usize,
+}
+
+// A call pushes a frame, and a return pops the innermost one
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CallEvent {
+    Call(Frame),
+    Return(Frame),
+}
+
+// What one instruction did to the call stack, so that it can be undone
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum StackChange {
+    Pushed { dropped: Option<Frame> },
+    Popped(Frame),
+}
+
+// A shadow call stack, rebuilt from the usual Intcode calling convention:
+// the caller stores its return address in a relative cell, typically
+// [rb+0] after moving rb with arb, and the very next instruction jumps to
+// the subroutine. The subroutine returns by jumping to the address held in
+// that cell, which is the innermost frame's return address.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub(crate) struct CallStack {
+    frames: Vec<Frame>,
+    // The value the previous instruction wrote to a relative cell
+    stored: Option<i64>,
+}
+
+impl CallStack {
+    pub(crate) fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub(crate) fn stored(&self) -> Option<i64> {
+        self.stored
+    }
+
+    // Updates the stack for an instruction about to execute at ip, and
+    // returns the call or return it made along with how to undo it
+    #[inline]
+    pub(crate) fn track(
+        &mut self,
+        ip: usize,
+        opcode: Opcode,
+        parameters: &[Parameter],
+        write: Option<(usize, i64)>,
+        new_ip: usize,
+    ) -> Option<(CallEvent, StackChange)> {
+        let stored = self.stored.take();
+        let next_ip = ip + 1 + opcode.parameter_count();
+        match opcode {
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse if new_ip != next_ip => {}
+            _ => {
+                if let (Some(offset), Some((_, value))) = (opcode.write_offset(), write) {
+                    if parameters[offset - 1].mode == Mode::Relative {
+                        self.stored = Some(value);
+                    }
+                }
+                return None;
+            }
+        }
+
+        if stored == Some(next_ip as i64) {
+            let frame = Frame {
+                entry: new_ip,
+                call_site: ip,
+                return_address: next_ip,
+            };
+            let dropped = if self.frames.len() == MAX_DEPTH {
+                Some(self.frames.remove(0))
+            } else {
+                None
+            };
+            self.frames.push(frame);
+            return Some((CallEvent::Call(frame), StackChange::Pushed { dropped }));
+        }
+
+        match self.frames.last() {
+            Some(&frame) if frame.return_address == new_ip => {
+                self.frames.pop();
+                Some((CallEvent::Return(frame), StackChange::Popped(frame)))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn undo(&mut self, change: Option<StackChange>, stored: Option<i64>) {
+        match change {
+            Some(StackChange::Pushed { dropped }) => {
+                self.frames.pop();
+                if let Some(frame) = dropped {
+                    self.frames.insert(0, frame);
+                }
+            }
+            Some(StackChange::Popped(frame)) => self.frames.push(frame),
+            None => {}
+        }
+        self.stored = stored;
+    }
+
+    // Describes the nesting of subroutines at ip, innermost first, for
+    // example
+    //
+    //     at 34 in subroutine 26
+    //     called from 20 in subroutine 40
+    //     called from 8 in main
+    pub(crate) fn backtrace(&self, ip: usize) -> String {
+        backtrace(ip, &self.frames)
+    }
+}
+
+// Describes the nesting of subroutines given by frames, outermost first, at
+// ip; see CallStack::backtrace
+pub(crate) fn backtrace(ip: usize, frames: &[Frame]) -> String {
+    let mut lines = Vec::new();
+    let mut address = format!("at {}", ip);
+    for frame in frames.iter().rev() {
+        lines.push(format!("{} in subroutine {}", address, frame.entry));
+        address = format!("called from {}", frame.call_site);
+    }
+    lines.push(format!("{} in main", address));
+
+    lines.join("\n")
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+// Values keyed by index. Indexes below DENSE are kept in a table indexed
+// directly, and those above it in a HashMap, so that a distant index does
+// not allocate a table entry for every index before it.
+#[derive(Debug, Clone)]
+pub(crate) struct IndexTable<T, const DENSE: usize> {
+    dense: Vec<Option<T>>,
+    distant: HashMap<usize, T>,
+}
+
+impl<T, const DENSE: usize> Default for IndexTable<T, DENSE> {
+    fn default() -> IndexTable<T, DENSE> {
+        IndexTable {
+            dense: Vec::new(),
+            distant: HashMap::new(),
+        }
+    }
+}
+
+impl<T, const DENSE: usize> IndexTable<T, DENSE> {
+    #[inline]
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        if index < DENSE {
+            self.dense.get(index).and_then(|value| value.as_ref())
+        } else {
+            self.distant.get(&index)
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < DENSE {
+            self.dense.get_mut(index).and_then(|value| value.as_mut())
+        } else {
+            self.distant.get_mut(&index)
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get_or_insert_with<F: FnOnce() -> T>(&mut self, index: usize, f: F) -> &mut T {
+        if index >= DENSE {
+            return self.distant.entry(index).or_insert_with(f);
+        }
+        if index >= self.dense.len() {
+            self.dense.resize_with(index + 1, || None);
+        }
+
+        self.dense[index].get_or_insert_with(f)
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, value: T) {
+        if index >= DENSE {
+            self.distant.insert(index, value);
+            return;
+        }
+        if index >= self.dense.len() {
+            self.dense.resize_with(index + 1, || None);
+        }
+
+        self.dense[index] = Some(value);
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) -> Option<T> {
+        if index < DENSE {
+            self.dense.get_mut(index).and_then(|value| value.take())
+        } else {
+            self.distant.remove(&index)
+        }
+    }
+
+    // Drops every value at or above the given index
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.dense.truncate(len);
+        self.distant.retain(|&index, _| index < len);
+    }
+
+    // The values in index order
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        let mut distant: Vec<(usize, &T)> = self
+            .distant
+            .iter()
+            .map(|(&index, value)| (index, value))
+            .collect();
+        distant.sort_unstable_by_key(|&(index, _)| index);
+
+        self.dense
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.as_ref().map(|value| (index, value)))
+            .chain(distant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distant_indexes() {
+        let mut table: IndexTable<i64, 16> = IndexTable::default();
+        table.insert(1 << 40, 1);
+        table.insert(20, 2);
+        table.insert(3, 3);
+        *table.get_or_insert_with(17, || 0) += 4;
+        assert_eq!(table.dense.len(), 4);
+
+        assert_eq!(
+            table.iter().collect::<Vec<_>>(),
+            vec![(3, &3), (17, &4), (20, &2), (1 << 40, &1)]
+        );
+        assert_eq!(table.remove(3), Some(3));
+        assert_eq!(table.remove(3), None);
+        assert_eq!(table.get(1 << 40), Some(&1));
+
+        table.truncate(20);
+        assert_eq!(table.iter().collect::<Vec<_>>(), vec![(17, &4)]);
+    }
+}
@@ -0,0 +1,385 @@
+use std::io::Write;
+
+use crate::instruction::{Mode, Opcode, Parameter};
+use crate::stack::CallEvent;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MemoryWrite {
+    pub address: usize,
+    pub old: i64,
+    pub new: i64,
+}
+
+// One executed instruction: the machine state before it ran, what it read
+// and wrote, and the resulting ip and rb. The call stack is the entry
+// addresses of the subroutines the instruction ran in, outermost first.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceEvent {
+    pub ip: usize,
+    pub rb: i64,
+    pub instruction: i64,
+    pub opcode: Opcode,
+    pub parameters: Vec<Parameter>,
+    pub write: Option<MemoryWrite>,
+    pub input: Option<i64>,
+    pub output: Option<i64>,
+    pub new_ip: usize,
+    pub new_rb: i64,
+    pub call: Option<CallEvent>,
+    pub call_stack: Vec<usize>,
+}
+
+pub trait TraceSink {
+    fn record(&mut self, event: &TraceEvent);
+
+    // A Computer builds no events at all for a sink which is not enabled
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+pub struct NoTrace;
+
+impl TraceSink for NoTrace {
+    fn record(&mut self, _event: &TraceEvent) {}
+
+    fn enabled(&self) -> bool {
+        false
+    }
+}
+
+impl TraceSink for Vec<TraceEvent> {
+    fn record(&mut self, event: &TraceEvent) {
+        self.push(event.clone());
+    }
+}
+
+// Writes one human readable line per instruction, for example
+//
+//     ip:   4 rb:    1 | <1001> 100 1 100 | add [P|100|0] [I|1] | 100: 0 => 1
+//
+// followed by any call or return, and the call stack if it is not empty
+pub struct TextTrace<W> {
+    writer: W,
+}
+
+impl<W: Write> TextTrace<W> {
+    pub fn new(writer: W) -> TextTrace<W> {
+        TextTrace { writer }
+    }
+}
+
+impl<W: Write> TraceSink for TextTrace<W> {
+    fn record(&mut self, event: &TraceEvent) {
+        let mut line = format!(
+            "ip: {:>3} rb: {:>4} | <{}>",
+            event.ip, event.rb, event.instruction
+        );
+        for parameter in event.parameters.iter() {
+            line.push_str(&format!(" {}", parameter.integer));
+        }
+
+        line.push_str(&format!(" | {}", event.opcode.mnemonic()));
+        let read_count = match event.opcode.write_offset() {
+            Some(offset) => offset - 1,
+            None => event.parameters.len(),
+        };
+        for parameter in event.parameters[..read_count].iter() {
+            line.push_str(&format!(" {}", parameter));
+        }
+
+        if let Some(value) = event.input {
+            line.push_str(&format!(" | <<< {}", value));
+        }
+        if let Some(value) = event.output {
+            line.push_str(&format!(" | >>> {}", value));
+        }
+        if let Some(write) = event.write {
+            line.push_str(&format!(
+                " | {}: {} => {}",
+                write.address, write.old, write.new
+            ));
+        }
+        if let Opcode::JumpIfTrue | Opcode::JumpIfFalse = event.opcode {
+            line.push_str(&format!(" | ip {} => {}", event.ip, event.new_ip));
+        }
+        if event.new_rb != event.rb {
+            line.push_str(&format!(" | rb {} => {}", event.rb, event.new_rb));
+        }
+        match event.call {
+            Some(CallEvent::Call(frame)) => {
+                line.push_str(&format!(" | call {} from {}", frame.entry, frame.call_site))
+            }
+            Some(CallEvent::Return(frame)) => line.push_str(&format!(
+                " | return from {} to {}",
+                frame.entry, frame.return_address
+            )),
+            None => {}
+        }
+        if !event.call_stack.is_empty() {
+            let entries: Vec<String> = event
+                .call_stack
+                .iter()
+                .map(|entry| entry.to_string())
+                .collect();
+            line.push_str(&format!(" | in {}", entries.join(" > ")));
+        }
+
+        let _ = writeln!(self.writer, "{}", line);
+    }
+}
+
+// Writes one JSON object per instruction, one per line
+pub struct JsonTrace<W> {
+    writer: W,
+}
+
+impl<W: Write> JsonTrace<W> {
+    pub fn new(writer: W) -> JsonTrace<W> {
+        JsonTrace { writer }
+    }
+}
+
+fn json_option(value: Option<i64>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("null"),
+    }
+}
+
+impl<W: Write> TraceSink for JsonTrace<W> {
+    fn record(&mut self, event: &TraceEvent) {
+        let parameters: Vec<String> = event
+            .parameters
+            .iter()
+            .map(|parameter| {
+                format!(
+                    "{{\"integer\":{},\"mode\":\"{}\",\"address\":{},\"value\":{}}}",
+                    parameter.integer,
+                    match parameter.mode {
+                        Mode::Position => "position",
+                        Mode::Immediate => "immediate",
+                        Mode::Relative => "relative",
+                    },
+                    parameter.address,
+                    parameter.value
+                )
+            })
+            .collect();
+        let write = match event.write {
+            Some(write) => format!(
+                "{{\"address\":{},\"old\":{},\"new\":{}}}",
+                write.address, write.old, write.new
+            ),
+            None => String::from("null"),
+        };
+        let call = match event.call {
+            Some(CallEvent::Call(frame)) => format!(
+                "{{\"call\":{},\"from\":{},\"return_address\":{}}}",
+                frame.entry, frame.call_site, frame.return_address
+            ),
+            Some(CallEvent::Return(frame)) => format!(
+                "{{\"return\":{},\"to\":{}}}",
+                frame.entry, frame.return_address
+            ),
+            None => String::from("null"),
+        };
+        let call_stack: Vec<String> = event
+            .call_stack
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect();
+
+        let _ = writeln!(
+            self.writer,
+            "{{\"ip\":{},\"rb\":{},\"instruction\":{},\"opcode\":\"{}\",\"parameters\":[{}],\"write\":{},\"input\":{},\"output\":{},\"new_ip\":{},\"new_rb\":{},\"call\":{},\"call_stack\":[{}]}}",
+            event.ip,
+            event.rb,
+            event.instruction,
+            event.opcode.mnemonic(),
+            parameters.join(","),
+            write,
+            json_option(event.input),
+            json_option(event.output),
+            event.new_ip,
+            event.new_rb,
+            call,
+            call_stack.join(",")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::computer::Computer;
+    use crate::stack::Frame;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    // Keeps the events where the test can see them once the machine owns
+    // the sink
+    struct Shared(Arc<Mutex<Vec<TraceEvent>>>);
+
+    impl TraceSink for Shared {
+        fn record(&mut self, event: &TraceEvent) {
+            self.0.lock().unwrap().record(event);
+        }
+    }
+
+    // Calls a subroutine which multiplies two inputs
+    fn events() -> Vec<TraceEvent> {
+        let program = assemble(
+            "
+                    arb  #100
+                    add  #ret #0 @0
+                    jt   #1 #sub
+            ret:    hlt
+            sub:    in   a
+                    in   b
+                    mul  a b a
+                    out  a
+                    jt   #1 @0
+            a:      data 0
+            b:      data 0
+            ",
+        )
+        .unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut computer = Computer::new(
+            &program,
+            VecDeque::from([3, 4]),
+            Vec::new(),
+            Shared(Arc::clone(&events)),
+        );
+        computer.run().unwrap();
+
+        let events = events.lock().unwrap().clone();
+        events
+    }
+
+    #[test]
+    fn events_sink() {
+        let events = events();
+        assert_eq!(events.len(), 9);
+
+        let parameter = |integer, mode, address, value| Parameter {
+            integer,
+            mode,
+            address,
+            value,
+        };
+        assert_eq!(
+            events[2],
+            TraceEvent {
+                ip: 6,
+                rb: 100,
+                instruction: 1105,
+                opcode: Opcode::JumpIfTrue,
+                parameters: vec![
+                    parameter(1, Mode::Immediate, 7, 1),
+                    parameter(10, Mode::Immediate, 8, 10),
+                ],
+                write: None,
+                input: None,
+                output: None,
+                new_ip: 10,
+                new_rb: 100,
+                call: Some(CallEvent::Call(Frame {
+                    entry: 10,
+                    call_site: 6,
+                    return_address: 9,
+                })),
+                call_stack: vec![],
+            }
+        );
+        assert_eq!(
+            events[3],
+            TraceEvent {
+                ip: 10,
+                rb: 100,
+                instruction: 3,
+                opcode: Opcode::Input,
+                parameters: vec![parameter(23, Mode::Position, 23, 0)],
+                write: Some(MemoryWrite {
+                    address: 23,
+                    old: 0,
+                    new: 3,
+                }),
+                input: Some(3),
+                output: None,
+                new_ip: 12,
+                new_rb: 100,
+                call: None,
+                call_stack: vec![10],
+            }
+        );
+        assert_eq!(events[6].output, Some(12));
+        assert_eq!(
+            events[7].call,
+            Some(CallEvent::Return(Frame {
+                entry: 10,
+                call_site: 6,
+                return_address: 9,
+            }))
+        );
+    }
+
+    #[test]
+    fn text() {
+        let mut trace = TextTrace::new(Vec::new());
+        for event in events().iter() {
+            trace.record(event);
+        }
+        assert_eq!(
+            String::from_utf8(trace.writer).unwrap(),
+            "\
+ip:   0 rb:    0 | <109> 100 | arb [I|100] | rb 0 => 100
+ip:   2 rb:  100 | <21101> 9 0 0 | add [I|9] [I|0] | 100: 0 => 9
+ip:   6 rb:  100 | <1105> 1 10 | jt [I|1] [I|10] | ip 6 => 10 | call 10 from 6
+ip:  10 rb:  100 | <3> 23 | in | <<< 3 | 23: 0 => 3 | in 10
+ip:  12 rb:  100 | <3> 24 | in | <<< 4 | 24: 0 => 4 | in 10
+ip:  14 rb:  100 | <2> 23 24 23 | mul [P|23|3] [P|24|4] | 23: 3 => 12 | in 10
+ip:  18 rb:  100 | <4> 23 | out [P|23|12] | >>> 12 | in 10
+ip:  20 rb:  100 | <2105> 1 0 | jt [I|1] [R|100|9] | ip 20 => 9 | return from 10 to 9 | in 10
+ip:   9 rb:  100 | <99> | hlt
+"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let mut trace = JsonTrace::new(Vec::new());
+        for event in events().iter() {
+            trace.record(event);
+        }
+        let json = String::from_utf8(trace.writer).unwrap();
+        let lines: Vec<&str> = json.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(
+            lines[1],
+            r#"{"ip":2,"rb":100,"instruction":21101,"opcode":"add","parameters":[{"integer":9,"mode":"immediate","address":3,"value":9},{"integer":0,"mode":"immediate","address":4,"value":0},{"integer":0,"mode":"relative","address":100,"value":0}],"write":{"address":100,"old":0,"new":9},"input":null,"output":null,"new_ip":6,"new_rb":100,"call":null,"call_stack":[]}"#
+        );
+        assert_eq!(
+            lines[2],
+            r#"{"ip":6,"rb":100,"instruction":1105,"opcode":"jt","parameters":[{"integer":1,"mode":"immediate","address":7,"value":1},{"integer":10,"mode":"immediate","address":8,"value":10}],"write":null,"input":null,"output":null,"new_ip":10,"new_rb":100,"call":{"call":10,"from":6,"return_address":9},"call_stack":[]}"#
+        );
+        assert_eq!(
+            lines[3],
+            r#"{"ip":10,"rb":100,"instruction":3,"opcode":"in","parameters":[{"integer":23,"mode":"position","address":23,"value":0}],"write":{"address":23,"old":0,"new":3},"input":3,"output":null,"new_ip":12,"new_rb":100,"call":null,"call_stack":[10]}"#
+        );
+        assert_eq!(
+            lines[6],
+            r#"{"ip":18,"rb":100,"instruction":4,"opcode":"out","parameters":[{"integer":23,"mode":"position","address":23,"value":12}],"write":null,"input":null,"output":12,"new_ip":20,"new_rb":100,"call":null,"call_stack":[10]}"#
+        );
+        assert_eq!(
+            lines[7],
+            r#"{"ip":20,"rb":100,"instruction":2105,"opcode":"jt","parameters":[{"integer":1,"mode":"immediate","address":21,"value":1},{"integer":0,"mode":"relative","address":100,"value":9}],"write":null,"input":null,"output":null,"new_ip":9,"new_rb":100,"call":{"return":10,"to":9},"call_stack":[10]}"#
+        );
+        assert_eq!(
+            lines[8],
+            r#"{"ip":9,"rb":100,"instruction":99,"opcode":"hlt","parameters":[],"write":null,"input":null,"output":null,"new_ip":9,"new_rb":100,"call":null,"call_stack":[]}"#
+        );
+    }
+}
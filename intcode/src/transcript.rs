@@ -0,0 +1,365 @@
+// Every value a Computer read and wrote, in order, written as lines of
+// text:
+//
+//     intcode transcript 1
+//     in 1
+//     out 0,0,1,0*3,4
+//     in -1
+//
+// Each line after the header holds consecutive inputs or consecutive
+// outputs, with runs of zeros written as 0*count, as in snapshots.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::computer::Computer;
+use crate::error::TranscriptError;
+use crate::io::{Input, Output};
+use crate::memory::Memory;
+use crate::snapshot::{parse_integers, write_integers};
+
+const HEADER: &str = "intcode transcript 1";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TranscriptEvent {
+    Input(i64),
+    Output(i64),
+}
+
+impl fmt::Display for TranscriptEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscriptEvent::Input(value) => write!(f, "input {}", value),
+            TranscriptEvent::Output(value) => write!(f, "output {}", value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Transcript {
+    pub events: Vec<TranscriptEvent>,
+}
+
+impl Transcript {
+    pub fn new() -> Transcript {
+        Transcript::default()
+    }
+
+    pub fn push(&mut self, event: TranscriptEvent) {
+        self.events.push(event);
+    }
+
+    pub fn inputs(&self) -> Vec<i64> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                TranscriptEvent::Input(value) => Some(*value),
+                TranscriptEvent::Output(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn outputs(&self) -> Vec<i64> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                TranscriptEvent::Output(value) => Some(*value),
+                TranscriptEvent::Input(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Transcript> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+
+        let mut index = 0;
+        while index < self.events.len() {
+            let input = matches!(self.events[index], TranscriptEvent::Input(_));
+            let mut values = Vec::new();
+            while let Some(event) = self.events.get(index) {
+                match (input, event) {
+                    (true, TranscriptEvent::Input(value))
+                    | (false, TranscriptEvent::Output(value)) => values.push(*value),
+                    _ => break,
+                }
+                index += 1;
+            }
+            write_integers(f, if input { "in" } else { "out" }, &values)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Transcript {
+    type Err = TranscriptError;
+
+    fn from_str(s: &str) -> Result<Transcript, TranscriptError> {
+        let mut transcript = Transcript::new();
+        if s.lines().next().is_none() {
+            return Err(TranscriptError::MissingHeader);
+        }
+
+        for (index, text) in s.lines().enumerate() {
+            let invalid = || TranscriptError::InvalidLine {
+                line: index + 1,
+                text: text.to_string(),
+            };
+
+            if index == 0 {
+                if text.trim() != HEADER {
+                    return Err(invalid());
+                }
+                continue;
+            }
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let (name, rest) = text.split_once(' ').unwrap_or((text, ""));
+            let event = match name {
+                "in" => TranscriptEvent::Input,
+                "out" => TranscriptEvent::Output,
+                _ => return Err(invalid()),
+            };
+            let values = parse_integers(rest).ok_or_else(invalid)?;
+            transcript.events.extend(values.into_iter().map(event));
+        }
+
+        Ok(transcript)
+    }
+}
+
+// What a host does with the transcript of a machine it runs: nothing,
+// record it to a file, or replay it from one to check that the program
+// still behaves the same way
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Session {
+    Live,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Session {
+    // Reads "--record FILE" or "--replay FILE" from a host's command line
+    // arguments, not counting the program name. No arguments is a live
+    // session; anything else gives None.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Option<Session> {
+        let args: Vec<String> = args.into_iter().collect();
+        match args.as_slice() {
+            [] => Some(Session::Live),
+            [flag, path] if flag == "--record" => Some(Session::Record(PathBuf::from(path))),
+            [flag, path] if flag == "--replay" => Some(Session::Replay(PathBuf::from(path))),
+            _ => None,
+        }
+    }
+
+    // Starts recording, or loads the transcript and starts replaying it
+    pub fn start<I: Input, O: Output, M: Memory>(
+        &self,
+        computer: &mut Computer<I, O, M>,
+    ) -> io::Result<()> {
+        match self {
+            Session::Live => (),
+            Session::Record(_) => computer.set_recording(true),
+            Session::Replay(path) => computer.replay(Transcript::load(path)?),
+        }
+
+        Ok(())
+    }
+
+    // Saves what was recorded, or checks that the whole transcript was
+    // replayed
+    pub fn finish<I: Input, O: Output, M: Memory>(
+        &self,
+        computer: &mut Computer<I, O, M>,
+    ) -> io::Result<()> {
+        match self {
+            Session::Live => Ok(()),
+            Session::Record(path) => computer.take_transcript().save(path),
+            Session::Replay(_) => match computer.replay_remaining() {
+                0 => Ok(()),
+                remaining => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "Replay ended with {} events left in the transcript",
+                        remaining
+                    ),
+                )),
+            },
+        }
+    }
+}
+
+// A transcript being replayed, and how far the machine has got through it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Replay {
+    events: Vec<TranscriptEvent>,
+    position: usize,
+}
+
+impl Replay {
+    pub(crate) fn new(transcript: Transcript) -> Replay {
+        Replay {
+            events: transcript.events,
+            position: 0,
+        }
+    }
+
+    // The next event the machine should produce, with its position in the
+    // transcript
+    pub(crate) fn next(&self) -> Option<(usize, TranscriptEvent)> {
+        self.events
+            .get(self.position)
+            .map(|&event| (self.position, event))
+    }
+
+    pub(crate) fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    pub(crate) fn rewind(&mut self) {
+        self.position = self.position.saturating_sub(1);
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.events.len() - self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::computer::StepResult;
+    use crate::error::IntcodeError;
+    use crate::trace::NoTrace;
+    use std::collections::VecDeque;
+
+    // Outputs each value it reads until it reads a zero
+    fn echo() -> Vec<i64> {
+        assemble(
+            "
+            loop:   in   value
+                    jf   value #done
+                    out  value
+                    jt   #1 #loop
+            done:   hlt
+            value:  data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    fn record(inputs: &[i64]) -> Transcript {
+        let mut computer = Computer::new(
+            &echo(),
+            VecDeque::from(inputs.to_vec()),
+            Vec::new(),
+            NoTrace,
+        );
+        computer.set_recording(true);
+        computer.run().unwrap();
+        computer.take_transcript()
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let transcript = record(&[1, 2, 0]);
+        let text = transcript.to_string();
+        assert_eq!(
+            text,
+            "intcode transcript 1\nin 1\nout 1\nin 2\nout 2\nin 0\n"
+        );
+        assert_eq!(text.parse(), Ok(transcript));
+        assert_eq!(
+            "".parse::<Transcript>(),
+            Err(TranscriptError::MissingHeader)
+        );
+    }
+
+    #[test]
+    fn replay_feeds_inputs_and_checks_outputs() {
+        let mut computer = Computer::new(&echo(), VecDeque::new(), Vec::new(), NoTrace);
+        computer.replay(record(&[1, 2, 0]));
+        computer.run().unwrap();
+        assert_eq!(computer.output(), &vec![1, 2]);
+        assert_eq!(computer.replay_remaining(), 0);
+
+        let mut transcript = record(&[1, 2, 0]);
+        transcript.events[3] = TranscriptEvent::Output(5);
+        let mut computer = Computer::new(&echo(), VecDeque::new(), Vec::new(), NoTrace);
+        computer.replay(transcript);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::ReplayMismatch {
+                ip: 5,
+                instruction: 4,
+                event: 3,
+                expected: TranscriptEvent::Output(5),
+                output: Some(2)
+            })
+        );
+    }
+
+    #[test]
+    fn step_back_rewinds_a_replay() {
+        let mut computer = Computer::new(&echo(), VecDeque::new(), Vec::new(), NoTrace);
+        computer.set_history(true);
+        computer.replay(record(&[1, 2, 3, 0]));
+        let events = computer.replay_remaining();
+
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(1)));
+        while computer.instructions() > 0 {
+            computer.step_back();
+        }
+        assert_eq!(computer.replay_remaining(), events);
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(1)));
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(2)));
+    }
+
+    #[test]
+    fn undone_inputs_come_before_a_replay() {
+        let program = echo();
+        let mut computer = Computer::new(&program, VecDeque::from([7]), Vec::new(), NoTrace);
+        computer.set_history(true);
+        assert_eq!(computer.run_until_io(), Ok(StepResult::Output(7)));
+        assert!(computer.rewind_to(0));
+
+        computer.replay(record(&[1, 0]));
+        computer.step().unwrap();
+        assert_eq!(computer.get_value(program.len() - 1), 7);
+        assert_eq!(computer.replay_remaining(), 3);
+    }
+
+    #[test]
+    fn session_from_args() {
+        let args = |args: &[&str]| Session::from_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(args(&[]), Some(Session::Live));
+        assert_eq!(
+            args(&["--record", "game.txt"]),
+            Some(Session::Record(PathBuf::from("game.txt")))
+        );
+        assert_eq!(
+            args(&["--replay", "game.txt"]),
+            Some(Session::Replay(PathBuf::from("game.txt")))
+        );
+        assert_eq!(args(&["--replay"]), None);
+        assert_eq!(args(&["--play", "game.txt"]), None);
+    }
+}
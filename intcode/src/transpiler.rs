@@ -0,0 +1,468 @@
+use crate::disassembler::{disassemble, Line, Operand};
+use crate::instruction::Opcode;
+
+const HEADER: &str = "\
+// Generated by the intcode transpile binary. Regenerate it rather than
+// editing it.
+//
+// Each instruction found by a linear sweep of the program is compiled to a
+// match arm on ip. Input, output and halt instructions, addresses with no
+// arm, and instructions whose cells have been overwritten with something
+// else run on the intcode interpreter instead, as does the whole program
+// under widened arithmetic. Compiled instructions are not traced, profiled,
+// counted towards limits or kept in the history.
+
+#![allow(dead_code)]
+
+use intcode::{
+    Arithmetic, Computer, Input, IntcodeError, Memory, NoTrace, Output, PagedMemory, StepResult,
+};
+";
+
+const RUNTIME: &str = "
+// Program memory which notes the instructions whose cells are overwritten
+// with a different value, so that they are no longer run compiled
+pub struct Cells {
+    memory: PagedMemory,
+    modified: Vec<bool>,
+}
+
+impl Cells {
+    fn modified(&self, ip: usize) -> bool {
+        self.modified.get(ip).copied().unwrap_or(false)
+    }
+}
+
+impl Memory for Cells {
+    fn from_program(program: &[i64]) -> Cells {
+        Cells {
+            memory: PagedMemory::from_program(program),
+            modified: vec![false; PROGRAM.len()],
+        }
+    }
+
+    fn get(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    fn set(&mut self, address: usize, value: i64) {
+        self.set_wide(address, value.into());
+    }
+
+    fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.memory.truncate(len);
+    }
+
+    fn runs(&self) -> Vec<(usize, Vec<i64>)> {
+        self.memory.runs()
+    }
+
+    fn get_wide(&self, address: usize) -> i128 {
+        self.memory.get_wide(address)
+    }
+
+    fn set_wide(&mut self, address: usize, value: i128) {
+        if address < PROGRAM.len() && value != PROGRAM[address].into() && OWNER[address] != NONE {
+            self.modified[OWNER[address]] = true;
+        }
+        self.memory.set_wide(address, value);
+    }
+
+    fn wide_cells(&self) -> Vec<(usize, i128)> {
+        self.memory.wide_cells()
+    }
+}
+
+pub struct Program<I, O> {
+    computer: Computer<I, O, Cells>,
+}
+
+impl<I: Input, O: Output> Program<I, O> {
+    pub fn new(input: I, output: O) -> Program<I, O> {
+        Program {
+            computer: Computer::with_memory(Cells::from_program(&PROGRAM), input, output, NoTrace),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        loop {
+            match self.run_until_io()? {
+                StepResult::NeedsInput => {
+                    return Err(IntcodeError::InputUnavailable {
+                        ip: self.computer.ip(),
+                        instruction: self.computer.get_value(self.computer.ip()),
+                    })
+                }
+                StepResult::Output(value) => self.computer.output_mut().write(value),
+                StepResult::Halted => return Ok(()),
+            }
+        }
+    }
+
+    pub fn run_until_io(&mut self) -> Result<StepResult, IntcodeError> {
+        loop {
+            if let Some(step_result) = self.step()? {
+                return Ok(step_result);
+            }
+        }
+    }
+
+    // The interpreter underneath, which holds the memory, registers and
+    // input and output
+    pub fn computer(&self) -> &Computer<I, O, Cells> {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut Computer<I, O, Cells> {
+        &mut self.computer
+    }
+
+    pub fn input(&self) -> &I {
+        self.computer.input()
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        self.computer.input_mut()
+    }
+
+    pub fn output(&self) -> &O {
+        self.computer.output()
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        self.computer.output_mut()
+    }
+
+    pub fn into_io(self) -> (I, O) {
+        self.computer.into_io()
+    }
+
+    pub fn get_value(&self, address: usize) -> i64 {
+        self.computer.get_value(address)
+    }
+
+    pub fn set_value(&mut self, address: usize, value: i64) {
+        self.computer.set_value(address, value);
+    }
+
+    // The relative base moved by integer, checked as the interpreter checks
+    // it
+    fn relative(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        let rb = self.computer.rb();
+        rb.checked_add(integer).ok_or(IntcodeError::RelativeOverflow {
+            ip,
+            instruction,
+            offset,
+            rb,
+            integer,
+        })
+    }
+
+    // The address of a relative mode operand
+    fn address(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<usize, IntcodeError> {
+        let address = self.relative(ip, instruction, offset, integer)?;
+        if address < 0 {
+            Err(IntcodeError::NegativeAddress {
+                ip,
+                instruction,
+                offset,
+                address,
+            })
+        } else {
+            Ok(address as usize)
+        }
+    }
+
+    fn read(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        Ok(self.computer.get_value(self.address(ip, instruction, offset, integer)?))
+    }
+
+    // Adds as the interpreter does, following its arithmetic policy. The
+    // result fits a cell, since compiled code does not run under widened
+    // arithmetic.
+    fn add(&self, ip: usize, instruction: i64, left: i64, right: i64) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .add(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn multiply(
+        &self,
+        ip: usize,
+        instruction: i64,
+        left: i64,
+        right: i64,
+    ) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .multiply(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn jump(&mut self, ip: usize, instruction: i64, target: i64) -> Result<(), IntcodeError> {
+        if target < 0 {
+            return Err(IntcodeError::NegativeJump {
+                ip,
+                instruction,
+                offset: 2,
+                target,
+            });
+        }
+        self.computer.set_ip(target as usize);
+
+        Ok(())
+    }
+";
+
+// How a compiled instruction reads one of its operands
+fn read_operand(address: usize, instruction: i64, offset: usize, operand: Operand) -> String {
+    match operand {
+        Operand::Position(integer) => format!("self.computer.get_value({})", integer),
+        Operand::Immediate(integer) => integer.to_string(),
+        Operand::Relative(integer) => format!(
+            "self.read({}, {}, {}, {})?",
+            address, instruction, offset, integer
+        ),
+    }
+}
+
+// The address a compiled instruction writes to. Immediate mode writes land
+// on the operand's own cell, as they do in the interpreter.
+fn write_address(address: usize, instruction: i64, offset: usize, operand: Operand) -> String {
+    match operand {
+        Operand::Position(integer) => integer.to_string(),
+        Operand::Immediate(_) => (address + offset).to_string(),
+        Operand::Relative(integer) => format!(
+            "self.address({}, {}, {}, {})?",
+            address, instruction, offset, integer
+        ),
+    }
+}
+
+// The body of the match arm for an instruction, or None if it runs on the
+// interpreter
+fn compile(
+    address: usize,
+    instruction: i64,
+    opcode: Opcode,
+    operands: &[Operand],
+) -> Option<String> {
+    // A negative position operand is an error the interpreter reports
+    if operands
+        .iter()
+        .any(|operand| matches!(operand, Operand::Position(integer) if *integer < 0))
+    {
+        return None;
+    }
+
+    let read = |index: usize| read_operand(address, instruction, index + 1, operands[index]);
+    let next_ip = address + 1 + opcode.parameter_count();
+
+    let body = match opcode {
+        Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equal => {
+            let value = match opcode {
+                Opcode::Add => format!("self.add({}, {}, a, b)?", address, instruction),
+                Opcode::Multiply => format!("self.multiply({}, {}, a, b)?", address, instruction),
+                Opcode::LessThan => String::from("(a < b) as i64"),
+                _ => String::from("(a == b) as i64"),
+            };
+            format!(
+                "let a = {};
+                let b = {};
+                let c = {};
+                self.computer.set_value(c, {});
+                self.computer.set_ip({});",
+                read(0),
+                read(1),
+                write_address(address, instruction, 3, operands[2]),
+                value,
+                next_ip
+            )
+        }
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+            let jump = match operands[1] {
+                Operand::Immediate(target) if target >= 0 => {
+                    format!("self.computer.set_ip({});", target)
+                }
+                _ => format!("self.jump({}, {}, {})?;", address, instruction, read(1)),
+            };
+            let comparison = if opcode == Opcode::JumpIfTrue {
+                "!="
+            } else {
+                "=="
+            };
+
+            match operands[0] {
+                // A constant condition either always jumps or never does
+                Operand::Immediate(integer) => {
+                    if (integer != 0) == (opcode == Opcode::JumpIfTrue) {
+                        jump
+                    } else {
+                        format!("self.computer.set_ip({});", next_ip)
+                    }
+                }
+                _ => format!(
+                    "if {} {} 0 {{
+                    {}
+                }} else {{
+                    self.computer.set_ip({});
+                }}",
+                    read(0),
+                    comparison,
+                    jump,
+                    next_ip
+                ),
+            }
+        }
+        Opcode::AdjustRelativeBase => format!(
+            "let a = {};
+                let rb = self.relative({}, {}, 1, a)?;
+                self.computer.set_rb(rb);
+                self.computer.set_ip({});",
+            read(0),
+            address,
+            instruction,
+            next_ip
+        ),
+        Opcode::Input | Opcode::Output | Opcode::Halt => return None,
+    };
+
+    Some(body)
+}
+
+fn table<T: ToString>(name: &str, kind: &str, values: &[T]) -> String {
+    let mut text = format!("const {}: [{}; {}] = [", name, kind, values.len());
+    for (index, value) in values.iter().enumerate() {
+        text.push_str(if index % 16 == 0 { "\n    " } else { " " });
+        text.push_str(&value.to_string());
+        text.push(',');
+    }
+    text.push_str("\n];\n");
+
+    text
+}
+
+// Translates a program into the source of a Rust module, which defines a
+// Program type with the same running and input and output methods as
+// Computer. The module depends on the intcode crate for its interpreter.
+pub fn transpile(program: &[i64]) -> String {
+    let lines = disassemble(program);
+
+    let mut owner = vec![usize::MAX; program.len()];
+    let mut arms = String::new();
+    for line in lines.iter() {
+        if let Line::Instruction {
+            address,
+            opcode,
+            operands,
+        } = line
+        {
+            let body = match compile(*address, program[*address], *opcode, operands) {
+                Some(body) => body,
+                None => continue,
+            };
+            for cell in owner[*address..*address + line.len()].iter_mut() {
+                *cell = *address;
+            }
+            arms.push_str(&format!(
+                "
+            // {}
+            {} => {{
+                {}
+            }}",
+                line.to_string().trim_start(),
+                address,
+                body
+            ));
+        }
+    }
+
+    let mut text = String::from(HEADER);
+    text.push('\n');
+    text.push_str(&table("PROGRAM", "i64", program));
+    text.push_str(
+        "
+// The address of the compiled instruction each program cell belongs to,
+// or NONE
+const NONE: usize = usize::MAX;
+",
+    );
+    let owner: Vec<String> = owner
+        .iter()
+        .map(|&address| {
+            if address == usize::MAX {
+                String::from("NONE")
+            } else {
+                address.to_string()
+            }
+        })
+        .collect();
+    text.push_str(&table("OWNER", "usize", &owner));
+    text.push_str(RUNTIME);
+    let body = if arms.is_empty() {
+        String::from("self.computer.step()")
+    } else {
+        format!(
+            "let ip = self.computer.ip();
+        if self.computer.memory().modified(ip)
+            || self.computer.arithmetic() == Arithmetic::Widened
+        {{
+            return self.computer.step();
+        }}
+
+        match ip {{{}
+            _ => return self.computer.step(),
+        }}
+
+        Ok(None)",
+            arms
+        )
+    };
+    text.push_str(&format!(
+        "
+    // Executes a single instruction, as Computer::step does
+    pub fn step(&mut self) -> Result<Option<StepResult>, IntcodeError> {{
+        {}
+    }}
+}}
+",
+        body
+    ));
+
+    text
+}
@@ -0,0 +1,16 @@
+; Reads two values, then outputs whether their sum is less than their
+; product, the sum and the product
+        in   a
+        in   b
+        add  a b sum
+        mul  a b product
+        lt   sum product less
+        out  less
+        out  sum
+        out  product
+        hlt
+a:      data 0
+b:      data 0
+sum:    data 0
+product: data 0
+less:   data 0
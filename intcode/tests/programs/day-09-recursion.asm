@@ -0,0 +1,23 @@
+; Computes Fibonacci numbers by naive recursion, keeping its frames on the
+; relative base stack the way the BOOST program's part 2 does
+        arb  #stack
+        in   @1
+        add  #ret #0 @0
+        jt   #1 #fib
+ret:    out  @1
+        hlt
+; [rb+0] return address, [rb+1] n on entry and fib(n) on return
+fib:    lt   @1 #2 @2
+        jt   @2 #done
+        arb  #3
+        add  @-2 #-1 @1
+        add  #ret1 #0 @0
+        jt   #1 #fib
+ret1:   add  @1 #0 @-1
+        add  @-2 #-2 @1
+        add  #ret2 #0 @0
+        jt   #1 #fib
+ret2:   add  @1 @-1 @-2
+        arb  #-3
+done:   jt   #1 @0
+stack:  data 0
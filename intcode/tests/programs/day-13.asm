@@ -0,0 +1,21 @@
+; Draws a paddle and a ball and updates the score once per frame, reading
+; the joystick in between, like the arcade cabinet's game loop
+loop:   in   joy
+        add  x joy x
+        out  x
+        out  #22
+        out  #3
+        out  #20
+        out  #21
+        out  #4
+        add  score #1 score
+        out  #-1
+        out  #0
+        out  score
+        add  frames #-1 frames
+        jt   frames #loop
+        hlt
+joy:    data 0
+x:      data 10
+score:  data 0
+frames: data 500
@@ -0,0 +1,13 @@
+; Moves the relative base by the two values it reads, then copies the cell
+; just past it to the output, so that hostile inputs take relative
+; addresses out of range
+        in   a
+        arb  a
+        in   b
+        arb  b
+        add  @1 #0 c
+        out  c
+        hlt
+a:      data 0
+b:      data 0
+c:      data 0
@@ -0,0 +1,309 @@
+// Runs transpiled programs against the interpreter. The modules under
+// transpiled/ are generated from the programs under programs/; if the
+// transpiler changes, regenerate them with
+//
+//     cargo run --bin transpile < tests/programs/day-05.txt > tests/transpiled/day_05.rs
+//     cargo run --bin transpile < tests/programs/day-09.txt > tests/transpiled/day_09.rs
+//     cargo run --bin assemble < tests/programs/day-09-recursion.asm \
+//         | cargo run --bin transpile > tests/transpiled/day_09_recursion.rs
+//     cargo run --bin assemble < tests/programs/day-13.asm \
+//         | cargo run --bin transpile > tests/transpiled/day_13.rs
+//     cargo run --bin assemble < tests/programs/relative-base.asm \
+//         | cargo run --bin transpile > tests/transpiled/relative_base.rs
+//     cargo run --bin assemble < tests/programs/arithmetic.asm \
+//         | cargo run --bin transpile > tests/transpiled/arithmetic.rs
+
+use intcode::{
+    assemble, parse_program, transpile, Arithmetic, Computer, IntcodeError, NoTrace, PagedMemory,
+    StepResult,
+};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fs;
+
+#[rustfmt::skip]
+#[path = "transpiled/arithmetic.rs"]
+mod arithmetic;
+#[rustfmt::skip]
+#[path = "transpiled/day_05.rs"]
+mod day_05;
+#[rustfmt::skip]
+#[path = "transpiled/day_09.rs"]
+mod day_09;
+#[rustfmt::skip]
+#[path = "transpiled/day_09_recursion.rs"]
+mod day_09_recursion;
+#[rustfmt::skip]
+#[path = "transpiled/day_13.rs"]
+mod day_13;
+#[rustfmt::skip]
+#[path = "transpiled/relative_base.rs"]
+mod relative_base;
+
+type Queue = VecDeque<i64>;
+
+// What a host needs to drive either the interpreter or a transpiled
+// program
+trait Machine {
+    fn run_until_io(&mut self) -> Result<StepResult, IntcodeError>;
+    fn push_input(&mut self, value: i64);
+}
+
+impl Machine for Computer<Queue, Vec<i64>, PagedMemory> {
+    fn run_until_io(&mut self) -> Result<StepResult, IntcodeError> {
+        Computer::run_until_io(self)
+    }
+
+    fn push_input(&mut self, value: i64) {
+        self.input_mut().push_back(value);
+    }
+}
+
+macro_rules! transpiled_machine {
+    ($module:ident) => {
+        impl Machine for $module::Program<Queue, Vec<i64>> {
+            fn run_until_io(&mut self) -> Result<StepResult, IntcodeError> {
+                $module::Program::run_until_io(self)
+            }
+
+            fn push_input(&mut self, value: i64) {
+                self.input_mut().push_back(value);
+            }
+        }
+    };
+}
+
+transpiled_machine!(arithmetic);
+transpiled_machine!(day_05);
+transpiled_machine!(day_09);
+transpiled_machine!(day_09_recursion);
+transpiled_machine!(day_13);
+transpiled_machine!(relative_base);
+
+fn program(name: &str) -> Vec<i64> {
+    let text = fs::read_to_string(format!("tests/programs/{}", name)).unwrap();
+    if name.ends_with(".asm") {
+        assemble(&text).unwrap()
+    } else {
+        parse_program(&text).unwrap()
+    }
+}
+
+fn interpreter(name: &str) -> Computer<Queue, Vec<i64>> {
+    Computer::new(&program(name), VecDeque::new(), Vec::new(), NoTrace)
+}
+
+// Runs a machine to the end, answering each read with the value input
+// gives for the outputs so far, and returns the outputs
+fn drive<M: Machine, F: FnMut(&[i64]) -> i64>(machine: &mut M, mut input: F) -> Vec<i64> {
+    let mut outputs = Vec::new();
+
+    loop {
+        match machine.run_until_io().unwrap() {
+            StepResult::NeedsInput => machine.push_input(input(&outputs)),
+            StepResult::Output(value) => outputs.push(value),
+            StepResult::Halted => return outputs,
+        }
+    }
+}
+
+// Steers the paddle towards the ball, as day-13 part 2 does
+fn joystick(outputs: &[i64]) -> i64 {
+    let (mut paddle, mut ball) = (0, 0);
+    for tile in outputs.chunks_exact(3) {
+        match tile {
+            [x, _, 3] => paddle = *x,
+            [x, _, 4] => ball = *x,
+            _ => (),
+        }
+    }
+
+    match paddle.cmp(&ball) {
+        Ordering::Less => 1,
+        Ordering::Equal => 0,
+        Ordering::Greater => -1,
+    }
+}
+
+#[test]
+fn generated_modules_are_current() {
+    for (name, module) in [
+        ("arithmetic.asm", "arithmetic.rs"),
+        ("day-05.txt", "day_05.rs"),
+        ("day-09.txt", "day_09.rs"),
+        ("day-09-recursion.asm", "day_09_recursion.rs"),
+        ("day-13.asm", "day_13.rs"),
+        ("relative-base.asm", "relative_base.rs"),
+    ] {
+        let generated = fs::read_to_string(format!("tests/transpiled/{}", module)).unwrap();
+        assert!(
+            transpile(&program(name)) == generated,
+            "tests/transpiled/{} is out of date; regenerate it from tests/programs/{}",
+            module,
+            name
+        );
+    }
+}
+
+#[test]
+fn day_05_comparisons() {
+    for value in [-5, 7, 8, 9, 1000] {
+        let expected = drive(&mut interpreter("day-05.txt"), |_| value);
+        let mut program = day_05::Program::new(VecDeque::new(), Vec::new());
+        assert_eq!(drive(&mut program, |_| value), expected);
+    }
+}
+
+#[test]
+fn day_09_quine() {
+    let expected = drive(&mut interpreter("day-09.txt"), |_| unreachable!());
+    assert_eq!(expected, program("day-09.txt"));
+    let mut program = day_09::Program::new(VecDeque::new(), Vec::new());
+    assert_eq!(drive(&mut program, |_| unreachable!()), expected);
+}
+
+#[test]
+fn day_09_recursion() {
+    for n in [0, 1, 2, 10, 20] {
+        let expected = drive(&mut interpreter("day-09-recursion.asm"), |_| n);
+        let mut program = day_09_recursion::Program::new(VecDeque::new(), Vec::new());
+        assert_eq!(drive(&mut program, |_| n), expected);
+    }
+}
+
+#[test]
+fn day_13_arcade() {
+    let expected = drive(&mut interpreter("day-13.asm"), joystick);
+    let mut program = day_13::Program::new(VecDeque::new(), Vec::new());
+    assert_eq!(drive(&mut program, joystick), expected);
+    assert_eq!(expected.last(), Some(&500));
+}
+
+#[test]
+fn relative_addresses_out_of_range() {
+    for inputs in [
+        [3, 4],
+        [-5, 0],
+        [i64::MAX, 0],
+        [i64::MAX, 1],
+        [i64::MIN, -1],
+    ] {
+        let mut interpreter = interpreter("relative-base.asm");
+        interpreter.input_mut().extend(inputs);
+        let mut program = relative_base::Program::new(VecDeque::from(inputs), Vec::new());
+
+        let expected = interpreter.run();
+        assert_eq!(program.run(), expected, "for inputs {:?}", inputs);
+        assert_eq!(program.output(), interpreter.output());
+    }
+
+    let mut program = relative_base::Program::new(VecDeque::from([i64::MAX, 1]), Vec::new());
+    assert_eq!(
+        program.run(),
+        Err(IntcodeError::RelativeOverflow {
+            ip: 6,
+            instruction: 9,
+            offset: 1,
+            rb: i64::MAX,
+            integer: 1
+        })
+    );
+}
+
+// Runs the arithmetic program on the interpreter and transpiled, under the
+// given policy, and checks that both give the same outputs and result
+fn arithmetic(policy: Arithmetic, a: i64, b: i64) -> (Vec<i64>, Result<(), IntcodeError>) {
+    let mut interpreter = interpreter("arithmetic.asm");
+    interpreter.set_arithmetic(policy);
+    interpreter.input_mut().extend([a, b]);
+    let expected = interpreter.run();
+
+    let mut program = arithmetic::Program::new(VecDeque::from([a, b]), Vec::new());
+    program.computer_mut().set_arithmetic(policy);
+    assert_eq!(program.run(), expected);
+    assert_eq!(program.output(), interpreter.output());
+
+    (program.output().clone(), expected)
+}
+
+#[test]
+fn checked_arithmetic() {
+    assert_eq!(
+        arithmetic(Arithmetic::Checked, 3, 4),
+        (vec![1, 7, 12], Ok(()))
+    );
+    assert_eq!(
+        arithmetic(Arithmetic::Checked, i64::MAX, 1),
+        (
+            vec![],
+            Err(IntcodeError::Overflow {
+                ip: 4,
+                instruction: 1,
+                left: i64::MAX.into(),
+                right: 1
+            })
+        )
+    );
+    assert_eq!(
+        arithmetic(Arithmetic::Checked, 1 << 32, 1 << 31),
+        (
+            vec![],
+            Err(IntcodeError::Overflow {
+                ip: 8,
+                instruction: 2,
+                left: 1 << 32,
+                right: 1 << 31
+            })
+        )
+    );
+    assert_eq!(
+        arithmetic(Arithmetic::Wrapping, 1 << 32, 1 << 32),
+        (vec![0, 1 << 33, 0], Ok(()))
+    );
+}
+
+#[test]
+fn widened_arithmetic() {
+    // The product is kept whole, so the comparison sees it, but it cannot
+    // be output
+    assert_eq!(
+        arithmetic(Arithmetic::Widened, 1 << 32, 1 << 32),
+        (
+            vec![1, 1 << 33],
+            Err(IntcodeError::WideValue {
+                ip: 20,
+                instruction: 4,
+                offset: 1,
+                value: 1 << 64
+            })
+        )
+    );
+    assert_eq!(
+        arithmetic(Arithmetic::Widened, i64::MIN, i64::MIN),
+        (
+            vec![1],
+            Err(IntcodeError::WideValue {
+                ip: 18,
+                instruction: 4,
+                offset: 1,
+                value: i128::from(i64::MIN) * 2
+            })
+        )
+    );
+}
+
+#[test]
+fn modified_instructions_fall_back_to_the_interpreter() {
+    // Patches the jump at 6 in the day-05 comparison into an output of 7
+    // and a halt
+    let mut interpreter = interpreter("day-05.txt");
+    let mut program = day_05::Program::new(VecDeque::new(), Vec::new());
+    for (address, value) in [(6, 104), (7, 7), (8, 99)] {
+        interpreter.set_value(address, value);
+        program.set_value(address, value);
+    }
+
+    let expected = drive(&mut interpreter, |_| 8);
+    assert_eq!(expected, vec![7]);
+    assert_eq!(drive(&mut program, |_| 8), expected);
+}
@@ -0,0 +1,297 @@
+// Generated by the intcode transpile binary. Regenerate it rather than
+// editing it.
+//
+// Each instruction found by a linear sweep of the program is compiled to a
+// match arm on ip. Input, output and halt instructions, addresses with no
+// arm, and instructions whose cells have been overwritten with something
+// else run on the intcode interpreter instead, as does the whole program
+// under widened arithmetic. Compiled instructions are not traced, profiled,
+// counted towards limits or kept in the history.
+
+#![allow(dead_code)]
+
+use intcode::{
+    Arithmetic, Computer, Input, IntcodeError, Memory, NoTrace, Output, PagedMemory, StepResult,
+};
+
+const PROGRAM: [i64; 28] = [
+    3, 23, 3, 24, 1, 23, 24, 25, 2, 23, 24, 26, 7, 25, 26, 27,
+    4, 27, 4, 25, 4, 26, 99, 0, 0, 0, 0, 0,
+];
+
+// The address of the compiled instruction each program cell belongs to,
+// or NONE
+const NONE: usize = usize::MAX;
+const OWNER: [usize; 28] = [
+    NONE, NONE, NONE, NONE, 4, 4, 4, 4, 8, 8, 8, 8, 12, 12, 12, 12,
+    NONE, NONE, NONE, NONE, NONE, NONE, NONE, NONE, NONE, NONE, NONE, NONE,
+];
+
+// Program memory which notes the instructions whose cells are overwritten
+// with a different value, so that they are no longer run compiled
+pub struct Cells {
+    memory: PagedMemory,
+    modified: Vec<bool>,
+}
+
+impl Cells {
+    fn modified(&self, ip: usize) -> bool {
+        self.modified.get(ip).copied().unwrap_or(false)
+    }
+}
+
+impl Memory for Cells {
+    fn from_program(program: &[i64]) -> Cells {
+        Cells {
+            memory: PagedMemory::from_program(program),
+            modified: vec![false; PROGRAM.len()],
+        }
+    }
+
+    fn get(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    fn set(&mut self, address: usize, value: i64) {
+        self.set_wide(address, value.into());
+    }
+
+    fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.memory.truncate(len);
+    }
+
+    fn runs(&self) -> Vec<(usize, Vec<i64>)> {
+        self.memory.runs()
+    }
+
+    fn get_wide(&self, address: usize) -> i128 {
+        self.memory.get_wide(address)
+    }
+
+    fn set_wide(&mut self, address: usize, value: i128) {
+        if address < PROGRAM.len() && value != PROGRAM[address].into() && OWNER[address] != NONE {
+            self.modified[OWNER[address]] = true;
+        }
+        self.memory.set_wide(address, value);
+    }
+
+    fn wide_cells(&self) -> Vec<(usize, i128)> {
+        self.memory.wide_cells()
+    }
+}
+
+pub struct Program<I, O> {
+    computer: Computer<I, O, Cells>,
+}
+
+impl<I: Input, O: Output> Program<I, O> {
+    pub fn new(input: I, output: O) -> Program<I, O> {
+        Program {
+            computer: Computer::with_memory(Cells::from_program(&PROGRAM), input, output, NoTrace),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        loop {
+            match self.run_until_io()? {
+                StepResult::NeedsInput => {
+                    return Err(IntcodeError::InputUnavailable {
+                        ip: self.computer.ip(),
+                        instruction: self.computer.get_value(self.computer.ip()),
+                    })
+                }
+                StepResult::Output(value) => self.computer.output_mut().write(value),
+                StepResult::Halted => return Ok(()),
+            }
+        }
+    }
+
+    pub fn run_until_io(&mut self) -> Result<StepResult, IntcodeError> {
+        loop {
+            if let Some(step_result) = self.step()? {
+                return Ok(step_result);
+            }
+        }
+    }
+
+    // The interpreter underneath, which holds the memory, registers and
+    // input and output
+    pub fn computer(&self) -> &Computer<I, O, Cells> {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut Computer<I, O, Cells> {
+        &mut self.computer
+    }
+
+    pub fn input(&self) -> &I {
+        self.computer.input()
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        self.computer.input_mut()
+    }
+
+    pub fn output(&self) -> &O {
+        self.computer.output()
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        self.computer.output_mut()
+    }
+
+    pub fn into_io(self) -> (I, O) {
+        self.computer.into_io()
+    }
+
+    pub fn get_value(&self, address: usize) -> i64 {
+        self.computer.get_value(address)
+    }
+
+    pub fn set_value(&mut self, address: usize, value: i64) {
+        self.computer.set_value(address, value);
+    }
+
+    // The relative base moved by integer, checked as the interpreter checks
+    // it
+    fn relative(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        let rb = self.computer.rb();
+        rb.checked_add(integer).ok_or(IntcodeError::RelativeOverflow {
+            ip,
+            instruction,
+            offset,
+            rb,
+            integer,
+        })
+    }
+
+    // The address of a relative mode operand
+    fn address(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<usize, IntcodeError> {
+        let address = self.relative(ip, instruction, offset, integer)?;
+        if address < 0 {
+            Err(IntcodeError::NegativeAddress {
+                ip,
+                instruction,
+                offset,
+                address,
+            })
+        } else {
+            Ok(address as usize)
+        }
+    }
+
+    fn read(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        Ok(self.computer.get_value(self.address(ip, instruction, offset, integer)?))
+    }
+
+    // Adds as the interpreter does, following its arithmetic policy. The
+    // result fits a cell, since compiled code does not run under widened
+    // arithmetic.
+    fn add(&self, ip: usize, instruction: i64, left: i64, right: i64) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .add(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn multiply(
+        &self,
+        ip: usize,
+        instruction: i64,
+        left: i64,
+        right: i64,
+    ) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .multiply(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn jump(&mut self, ip: usize, instruction: i64, target: i64) -> Result<(), IntcodeError> {
+        if target < 0 {
+            return Err(IntcodeError::NegativeJump {
+                ip,
+                instruction,
+                offset: 2,
+                target,
+            });
+        }
+        self.computer.set_ip(target as usize);
+
+        Ok(())
+    }
+
+    // Executes a single instruction, as Computer::step does
+    pub fn step(&mut self) -> Result<Option<StepResult>, IntcodeError> {
+        let ip = self.computer.ip();
+        if self.computer.memory().modified(ip)
+            || self.computer.arithmetic() == Arithmetic::Widened
+        {
+            return self.computer.step();
+        }
+
+        match ip {
+            // 4: add  [P|23] [P|24] [P|25]
+            4 => {
+                let a = self.computer.get_value(23);
+                let b = self.computer.get_value(24);
+                let c = 25;
+                self.computer.set_value(c, self.add(4, 1, a, b)?);
+                self.computer.set_ip(8);
+            }
+            // 8: mul  [P|23] [P|24] [P|26]
+            8 => {
+                let a = self.computer.get_value(23);
+                let b = self.computer.get_value(24);
+                let c = 26;
+                self.computer.set_value(c, self.multiply(8, 2, a, b)?);
+                self.computer.set_ip(12);
+            }
+            // 12: lt   [P|25] [P|26] [P|27]
+            12 => {
+                let a = self.computer.get_value(25);
+                let b = self.computer.get_value(26);
+                let c = 27;
+                self.computer.set_value(c, (a < b) as i64);
+                self.computer.set_ip(16);
+            }
+            _ => return self.computer.step(),
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,339 @@
+// Generated by the intcode transpile binary. Regenerate it rather than
+// editing it.
+//
+// Each instruction found by a linear sweep of the program is compiled to a
+// match arm on ip. Input, output and halt instructions, addresses with no
+// arm, and instructions whose cells have been overwritten with something
+// else run on the intcode interpreter instead, as does the whole program
+// under widened arithmetic. Compiled instructions are not traced, profiled,
+// counted towards limits or kept in the history.
+
+#![allow(dead_code)]
+
+use intcode::{
+    Arithmetic, Computer, Input, IntcodeError, Memory, NoTrace, Output, PagedMemory, StepResult,
+};
+
+const PROGRAM: [i64; 47] = [
+    3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
+    1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104,
+    999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99,
+];
+
+// The address of the compiled instruction each program cell belongs to,
+// or NONE
+const NONE: usize = usize::MAX;
+const OWNER: [usize; 47] = [
+    NONE, NONE, 2, 2, 2, 2, 6, 6, 6, 9, 9, 9, 9, 13, 13, 13,
+    16, 16, 16, NONE, NONE, NONE, 22, 22, 22, 22, NONE, NONE, 28, 28, 28, NONE,
+    NONE, 33, 33, 33, 36, 36, 36, 36, NONE, NONE, 42, 42, 42, NONE, NONE,
+];
+
+// Program memory which notes the instructions whose cells are overwritten
+// with a different value, so that they are no longer run compiled
+pub struct Cells {
+    memory: PagedMemory,
+    modified: Vec<bool>,
+}
+
+impl Cells {
+    fn modified(&self, ip: usize) -> bool {
+        self.modified.get(ip).copied().unwrap_or(false)
+    }
+}
+
+impl Memory for Cells {
+    fn from_program(program: &[i64]) -> Cells {
+        Cells {
+            memory: PagedMemory::from_program(program),
+            modified: vec![false; PROGRAM.len()],
+        }
+    }
+
+    fn get(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    fn set(&mut self, address: usize, value: i64) {
+        self.set_wide(address, value.into());
+    }
+
+    fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.memory.truncate(len);
+    }
+
+    fn runs(&self) -> Vec<(usize, Vec<i64>)> {
+        self.memory.runs()
+    }
+
+    fn get_wide(&self, address: usize) -> i128 {
+        self.memory.get_wide(address)
+    }
+
+    fn set_wide(&mut self, address: usize, value: i128) {
+        if address < PROGRAM.len() && value != PROGRAM[address].into() && OWNER[address] != NONE {
+            self.modified[OWNER[address]] = true;
+        }
+        self.memory.set_wide(address, value);
+    }
+
+    fn wide_cells(&self) -> Vec<(usize, i128)> {
+        self.memory.wide_cells()
+    }
+}
+
+pub struct Program<I, O> {
+    computer: Computer<I, O, Cells>,
+}
+
+impl<I: Input, O: Output> Program<I, O> {
+    pub fn new(input: I, output: O) -> Program<I, O> {
+        Program {
+            computer: Computer::with_memory(Cells::from_program(&PROGRAM), input, output, NoTrace),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        loop {
+            match self.run_until_io()? {
+                StepResult::NeedsInput => {
+                    return Err(IntcodeError::InputUnavailable {
+                        ip: self.computer.ip(),
+                        instruction: self.computer.get_value(self.computer.ip()),
+                    })
+                }
+                StepResult::Output(value) => self.computer.output_mut().write(value),
+                StepResult::Halted => return Ok(()),
+            }
+        }
+    }
+
+    pub fn run_until_io(&mut self) -> Result<StepResult, IntcodeError> {
+        loop {
+            if let Some(step_result) = self.step()? {
+                return Ok(step_result);
+            }
+        }
+    }
+
+    // The interpreter underneath, which holds the memory, registers and
+    // input and output
+    pub fn computer(&self) -> &Computer<I, O, Cells> {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut Computer<I, O, Cells> {
+        &mut self.computer
+    }
+
+    pub fn input(&self) -> &I {
+        self.computer.input()
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        self.computer.input_mut()
+    }
+
+    pub fn output(&self) -> &O {
+        self.computer.output()
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        self.computer.output_mut()
+    }
+
+    pub fn into_io(self) -> (I, O) {
+        self.computer.into_io()
+    }
+
+    pub fn get_value(&self, address: usize) -> i64 {
+        self.computer.get_value(address)
+    }
+
+    pub fn set_value(&mut self, address: usize, value: i64) {
+        self.computer.set_value(address, value);
+    }
+
+    // The relative base moved by integer, checked as the interpreter checks
+    // it
+    fn relative(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        let rb = self.computer.rb();
+        rb.checked_add(integer).ok_or(IntcodeError::RelativeOverflow {
+            ip,
+            instruction,
+            offset,
+            rb,
+            integer,
+        })
+    }
+
+    // The address of a relative mode operand
+    fn address(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<usize, IntcodeError> {
+        let address = self.relative(ip, instruction, offset, integer)?;
+        if address < 0 {
+            Err(IntcodeError::NegativeAddress {
+                ip,
+                instruction,
+                offset,
+                address,
+            })
+        } else {
+            Ok(address as usize)
+        }
+    }
+
+    fn read(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        Ok(self.computer.get_value(self.address(ip, instruction, offset, integer)?))
+    }
+
+    // Adds as the interpreter does, following its arithmetic policy. The
+    // result fits a cell, since compiled code does not run under widened
+    // arithmetic.
+    fn add(&self, ip: usize, instruction: i64, left: i64, right: i64) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .add(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn multiply(
+        &self,
+        ip: usize,
+        instruction: i64,
+        left: i64,
+        right: i64,
+    ) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .multiply(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn jump(&mut self, ip: usize, instruction: i64, target: i64) -> Result<(), IntcodeError> {
+        if target < 0 {
+            return Err(IntcodeError::NegativeJump {
+                ip,
+                instruction,
+                offset: 2,
+                target,
+            });
+        }
+        self.computer.set_ip(target as usize);
+
+        Ok(())
+    }
+
+    // Executes a single instruction, as Computer::step does
+    pub fn step(&mut self) -> Result<Option<StepResult>, IntcodeError> {
+        let ip = self.computer.ip();
+        if self.computer.memory().modified(ip)
+            || self.computer.arithmetic() == Arithmetic::Widened
+        {
+            return self.computer.step();
+        }
+
+        match ip {
+            // 2: eq   [P|21] [I|8] [P|20]
+            2 => {
+                let a = self.computer.get_value(21);
+                let b = 8;
+                let c = 20;
+                self.computer.set_value(c, (a == b) as i64);
+                self.computer.set_ip(6);
+            }
+            // 6: jt   [P|20] [I|22]
+            6 => {
+                if self.computer.get_value(20) != 0 {
+                    self.computer.set_ip(22);
+                } else {
+                    self.computer.set_ip(9);
+                }
+            }
+            // 9: lt   [I|8] [P|21] [P|20]
+            9 => {
+                let a = 8;
+                let b = self.computer.get_value(21);
+                let c = 20;
+                self.computer.set_value(c, (a < b) as i64);
+                self.computer.set_ip(13);
+            }
+            // 13: jf   [P|20] [I|31]
+            13 => {
+                if self.computer.get_value(20) == 0 {
+                    self.computer.set_ip(31);
+                } else {
+                    self.computer.set_ip(16);
+                }
+            }
+            // 16: jf   [I|0] [I|36]
+            16 => {
+                self.computer.set_ip(36);
+            }
+            // 22: mul  [P|21] [I|125] [P|20]
+            22 => {
+                let a = self.computer.get_value(21);
+                let b = 125;
+                let c = 20;
+                self.computer.set_value(c, self.multiply(22, 1002, a, b)?);
+                self.computer.set_ip(26);
+            }
+            // 28: jt   [I|1] [I|46]
+            28 => {
+                self.computer.set_ip(46);
+            }
+            // 33: jt   [I|1] [I|46]
+            33 => {
+                self.computer.set_ip(46);
+            }
+            // 36: add  [I|1000] [I|1] [P|20]
+            36 => {
+                let a = 1000;
+                let b = 1;
+                let c = 20;
+                self.computer.set_value(c, self.add(36, 1101, a, b)?);
+                self.computer.set_ip(40);
+            }
+            // 42: jt   [I|1] [I|46]
+            42 => {
+                self.computer.set_ip(46);
+            }
+            _ => return self.computer.step(),
+        }
+
+        Ok(None)
+    }
+}
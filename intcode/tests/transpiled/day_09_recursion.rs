@@ -0,0 +1,386 @@
+// Generated by the intcode transpile binary. Regenerate it rather than
+// editing it.
+//
+// Each instruction found by a linear sweep of the program is compiled to a
+// match arm on ip. Input, output and halt instructions, addresses with no
+// arm, and instructions whose cells have been overwritten with something
+// else run on the intcode interpreter instead, as does the whole program
+// under widened arithmetic. Compiled instructions are not traced, profiled,
+// counted towards limits or kept in the history.
+
+#![allow(dead_code)]
+
+use intcode::{
+    Arithmetic, Computer, Input, IntcodeError, Memory, NoTrace, Output, PagedMemory, StepResult,
+};
+
+const PROGRAM: [i64; 59] = [
+    109, 58, 203, 1, 21101, 11, 0, 0, 1105, 1, 14, 204, 1, 99, 21207, 1,
+    2, 2, 1205, 2, 55, 109, 3, 21201, -2, -1, 1, 21101, 34, 0, 0, 1105,
+    1, 14, 21201, 1, 0, -1, 21201, -2, -2, 1, 21101, 49, 0, 0, 1105, 1,
+    14, 22201, 1, -1, -2, 109, -3, 2105, 1, 0, 0,
+];
+
+// The address of the compiled instruction each program cell belongs to,
+// or NONE
+const NONE: usize = usize::MAX;
+const OWNER: [usize; 59] = [
+    0, 0, NONE, NONE, 4, 4, 4, 4, 8, 8, 8, NONE, NONE, NONE, 14, 14,
+    14, 14, 18, 18, 18, 21, 21, 23, 23, 23, 23, 27, 27, 27, 27, 31,
+    31, 31, 34, 34, 34, 34, 38, 38, 38, 38, 42, 42, 42, 42, 46, 46,
+    46, 49, 49, 49, 49, 53, 53, 55, 55, 55, NONE,
+];
+
+// Program memory which notes the instructions whose cells are overwritten
+// with a different value, so that they are no longer run compiled
+pub struct Cells {
+    memory: PagedMemory,
+    modified: Vec<bool>,
+}
+
+impl Cells {
+    fn modified(&self, ip: usize) -> bool {
+        self.modified.get(ip).copied().unwrap_or(false)
+    }
+}
+
+impl Memory for Cells {
+    fn from_program(program: &[i64]) -> Cells {
+        Cells {
+            memory: PagedMemory::from_program(program),
+            modified: vec![false; PROGRAM.len()],
+        }
+    }
+
+    fn get(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    fn set(&mut self, address: usize, value: i64) {
+        self.set_wide(address, value.into());
+    }
+
+    fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.memory.truncate(len);
+    }
+
+    fn runs(&self) -> Vec<(usize, Vec<i64>)> {
+        self.memory.runs()
+    }
+
+    fn get_wide(&self, address: usize) -> i128 {
+        self.memory.get_wide(address)
+    }
+
+    fn set_wide(&mut self, address: usize, value: i128) {
+        if address < PROGRAM.len() && value != PROGRAM[address].into() && OWNER[address] != NONE {
+            self.modified[OWNER[address]] = true;
+        }
+        self.memory.set_wide(address, value);
+    }
+
+    fn wide_cells(&self) -> Vec<(usize, i128)> {
+        self.memory.wide_cells()
+    }
+}
+
+pub struct Program<I, O> {
+    computer: Computer<I, O, Cells>,
+}
+
+impl<I: Input, O: Output> Program<I, O> {
+    pub fn new(input: I, output: O) -> Program<I, O> {
+        Program {
+            computer: Computer::with_memory(Cells::from_program(&PROGRAM), input, output, NoTrace),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        loop {
+            match self.run_until_io()? {
+                StepResult::NeedsInput => {
+                    return Err(IntcodeError::InputUnavailable {
+                        ip: self.computer.ip(),
+                        instruction: self.computer.get_value(self.computer.ip()),
+                    })
+                }
+                StepResult::Output(value) => self.computer.output_mut().write(value),
+                StepResult::Halted => return Ok(()),
+            }
+        }
+    }
+
+    pub fn run_until_io(&mut self) -> Result<StepResult, IntcodeError> {
+        loop {
+            if let Some(step_result) = self.step()? {
+                return Ok(step_result);
+            }
+        }
+    }
+
+    // The interpreter underneath, which holds the memory, registers and
+    // input and output
+    pub fn computer(&self) -> &Computer<I, O, Cells> {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut Computer<I, O, Cells> {
+        &mut self.computer
+    }
+
+    pub fn input(&self) -> &I {
+        self.computer.input()
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        self.computer.input_mut()
+    }
+
+    pub fn output(&self) -> &O {
+        self.computer.output()
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        self.computer.output_mut()
+    }
+
+    pub fn into_io(self) -> (I, O) {
+        self.computer.into_io()
+    }
+
+    pub fn get_value(&self, address: usize) -> i64 {
+        self.computer.get_value(address)
+    }
+
+    pub fn set_value(&mut self, address: usize, value: i64) {
+        self.computer.set_value(address, value);
+    }
+
+    // The relative base moved by integer, checked as the interpreter checks
+    // it
+    fn relative(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        let rb = self.computer.rb();
+        rb.checked_add(integer).ok_or(IntcodeError::RelativeOverflow {
+            ip,
+            instruction,
+            offset,
+            rb,
+            integer,
+        })
+    }
+
+    // The address of a relative mode operand
+    fn address(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<usize, IntcodeError> {
+        let address = self.relative(ip, instruction, offset, integer)?;
+        if address < 0 {
+            Err(IntcodeError::NegativeAddress {
+                ip,
+                instruction,
+                offset,
+                address,
+            })
+        } else {
+            Ok(address as usize)
+        }
+    }
+
+    fn read(
+        &self,
+        ip: usize,
+        instruction: i64,
+        offset: usize,
+        integer: i64,
+    ) -> Result<i64, IntcodeError> {
+        Ok(self.computer.get_value(self.address(ip, instruction, offset, integer)?))
+    }
+
+    // Adds as the interpreter does, following its arithmetic policy. The
+    // result fits a cell, since compiled code does not run under widened
+    // arithmetic.
+    fn add(&self, ip: usize, instruction: i64, left: i64, right: i64) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .add(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn multiply(
+        &self,
+        ip: usize,
+        instruction: i64,
+        left: i64,
+        right: i64,
+    ) -> Result<i64, IntcodeError> {
+        self.computer
+            .arithmetic()
+            .multiply(left.into(), right.into())
+            .map(|value| value as i64)
+            .ok_or(IntcodeError::Overflow {
+                ip,
+                instruction,
+                left: left.into(),
+                right: right.into(),
+            })
+    }
+
+    fn jump(&mut self, ip: usize, instruction: i64, target: i64) -> Result<(), IntcodeError> {
+        if target < 0 {
+            return Err(IntcodeError::NegativeJump {
+                ip,
+                instruction,
+                offset: 2,
+                target,
+            });
+        }
+        self.computer.set_ip(target as usize);
+
+        Ok(())
+    }
+
+    // Executes a single instruction, as Computer::step does
+    pub fn step(&mut self) -> Result<Option<StepResult>, IntcodeError> {
+        let ip = self.computer.ip();
+        if self.computer.memory().modified(ip)
+            || self.computer.arithmetic() == Arithmetic::Widened
+        {
+            return self.computer.step();
+        }
+
+        match ip {
+            // 0: arb  [I|58]
+            0 => {
+                let a = 58;
+                let rb = self.relative(0, 109, 1, a)?;
+                self.computer.set_rb(rb);
+                self.computer.set_ip(2);
+            }
+            // 4: add  [I|11] [I|0] [R|0]
+            4 => {
+                let a = 11;
+                let b = 0;
+                let c = self.address(4, 21101, 3, 0)?;
+                self.computer.set_value(c, self.add(4, 21101, a, b)?);
+                self.computer.set_ip(8);
+            }
+            // 8: jt   [I|1] [I|14]
+            8 => {
+                self.computer.set_ip(14);
+            }
+            // 14: lt   [R|1] [I|2] [R|2]
+            14 => {
+                let a = self.read(14, 21207, 1, 1)?;
+                let b = 2;
+                let c = self.address(14, 21207, 3, 2)?;
+                self.computer.set_value(c, (a < b) as i64);
+                self.computer.set_ip(18);
+            }
+            // 18: jt   [R|2] [I|55]
+            18 => {
+                if self.read(18, 1205, 1, 2)? != 0 {
+                    self.computer.set_ip(55);
+                } else {
+                    self.computer.set_ip(21);
+                }
+            }
+            // 21: arb  [I|3]
+            21 => {
+                let a = 3;
+                let rb = self.relative(21, 109, 1, a)?;
+                self.computer.set_rb(rb);
+                self.computer.set_ip(23);
+            }
+            // 23: add  [R|-2] [I|-1] [R|1]
+            23 => {
+                let a = self.read(23, 21201, 1, -2)?;
+                let b = -1;
+                let c = self.address(23, 21201, 3, 1)?;
+                self.computer.set_value(c, self.add(23, 21201, a, b)?);
+                self.computer.set_ip(27);
+            }
+            // 27: add  [I|34] [I|0] [R|0]
+            27 => {
+                let a = 34;
+                let b = 0;
+                let c = self.address(27, 21101, 3, 0)?;
+                self.computer.set_value(c, self.add(27, 21101, a, b)?);
+                self.computer.set_ip(31);
+            }
+            // 31: jt   [I|1] [I|14]
+            31 => {
+                self.computer.set_ip(14);
+            }
+            // 34: add  [R|1] [I|0] [R|-1]
+            34 => {
+                let a = self.read(34, 21201, 1, 1)?;
+                let b = 0;
+                let c = self.address(34, 21201, 3, -1)?;
+                self.computer.set_value(c, self.add(34, 21201, a, b)?);
+                self.computer.set_ip(38);
+            }
+            // 38: add  [R|-2] [I|-2] [R|1]
+            38 => {
+                let a = self.read(38, 21201, 1, -2)?;
+                let b = -2;
+                let c = self.address(38, 21201, 3, 1)?;
+                self.computer.set_value(c, self.add(38, 21201, a, b)?);
+                self.computer.set_ip(42);
+            }
+            // 42: add  [I|49] [I|0] [R|0]
+            42 => {
+                let a = 49;
+                let b = 0;
+                let c = self.address(42, 21101, 3, 0)?;
+                self.computer.set_value(c, self.add(42, 21101, a, b)?);
+                self.computer.set_ip(46);
+            }
+            // 46: jt   [I|1] [I|14]
+            46 => {
+                self.computer.set_ip(14);
+            }
+            // 49: add  [R|1] [R|-1] [R|-2]
+            49 => {
+                let a = self.read(49, 22201, 1, 1)?;
+                let b = self.read(49, 22201, 2, -1)?;
+                let c = self.address(49, 22201, 3, -2)?;
+                self.computer.set_value(c, self.add(49, 22201, a, b)?);
+                self.computer.set_ip(53);
+            }
+            // 53: arb  [I|-3]
+            53 => {
+                let a = -3;
+                let rb = self.relative(53, 109, 1, a)?;
+                self.computer.set_rb(rb);
+                self.computer.set_ip(55);
+            }
+            // 55: jt   [I|1] [R|0]
+            55 => {
+                self.jump(55, 2105, self.read(55, 2105, 2, 0)?)?;
+            }
+            _ => return self.computer.step(),
+        }
+
+        Ok(None)
+    }
+}